@@ -0,0 +1,55 @@
+//! Compares the scalar and vectorized Pearson correlation on tile-sized inputs.
+//!
+//! Run with `cargo bench --features simd`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use typistapp::correlation::{correlation, correlation_scalar};
+
+/// The number of values in a tile of the default 20×20 cell.
+const TILE_VALUES: usize = 400;
+
+/// How many correlations each measurement runs.
+const ITERATIONS: u32 = 200_000;
+
+/// Returns `n` pseudo-random values in [0, 1) from a fixed seed.
+fn values(n: usize, seed: u64) -> Vec<f64> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        })
+        .collect()
+}
+
+/// Returns the mean time of one call of `f` over `ITERATIONS` calls, after a warm-up.
+fn measure(f: impl Fn(&[f64], &[f64]) -> Option<f64>, x: &[f64], y: &[f64]) -> Duration {
+    for _ in 0..ITERATIONS / 10 {
+        black_box(f(black_box(x), black_box(y)));
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f(black_box(x), black_box(y)));
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let x = values(TILE_VALUES, 1);
+    let y = values(TILE_VALUES, 2);
+
+    let scalar = measure(correlation_scalar, &x, &y);
+    let simd = measure(correlation, &x, &y);
+    println!("correlation of {TILE_VALUES} values:");
+    println!("  scalar: {scalar:?}");
+    println!("  simd:   {simd:?}");
+    println!(
+        "  speedup: {:.2}x",
+        scalar.as_secs_f64() / simd.as_secs_f64()
+    );
+}
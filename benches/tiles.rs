@@ -0,0 +1,48 @@
+//! Times the parallel extraction of picture tiles against a single thread.
+//!
+//! Run with `cargo bench --bench tiles`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use typistapp::{FONT_DATA, MAX_LENGTH, model::Model};
+
+/// How many extractions each measurement runs.
+const ITERATIONS: u32 = 20;
+
+/// Returns the mean time of one tile extraction over `ITERATIONS` runs on a
+/// pool of `threads` threads, after a warm-up.
+fn measure(model: &Model, threads: usize) -> Duration {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("a thread pool");
+    pool.install(|| {
+        black_box(model.tiles().expect("tiles"));
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(model.tiles().expect("tiles"));
+        }
+        start.elapsed() / ITERATIONS
+    })
+}
+
+fn main() {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2560, 1440, |x, y| {
+        let v = ((x ^ y) & 0xff) as u8;
+        Rgba([v, v.wrapping_mul(3), 255 - v, 255])
+    }));
+    let model = Model::new(MAX_LENGTH, &image, &['Ａ'], FONT_DATA).expect("a model");
+    let threads = rayon::current_num_threads();
+
+    let single = measure(&model, 1);
+    let parallel = measure(&model, threads);
+    println!("tiles of a 2560x1440 image at length {MAX_LENGTH}:");
+    println!("  single thread: {single:?}");
+    println!("  parallel ({threads} threads): {parallel:?}");
+    println!(
+        "  speedup: {:.2}x",
+        single.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -1,4 +1,10 @@
-use std::{io::Write, path::Path};
+use std::{io::Write, path::Path, thread, time::Duration};
+
+/// How many times the font download is attempted before the build fails.
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry, doubled for each further retry.
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
 
 fn main() {
     let out_dir = std::env::var("OUT_DIR").unwrap();
@@ -6,15 +12,43 @@ fn main() {
 
     if !font_path.exists() {
         let url = "https://raw.githubusercontent.com/notofonts/noto-cjk/main/Sans/SubsetOTF/JP/NotoSansJP-Regular.otf";
-        let response = ureq::get(url)
-            .call()
-            .expect("Failed to download font")
-            .body_mut()
-            .read_to_vec()
-            .expect("Failed to read font data");
+        let response = download(url);
 
         let mut file = std::fs::File::create(&font_path).expect("Failed to create font file");
         file.write_all(&response)
             .expect("Failed to write font data to file");
     }
 }
+
+/// Downloads `url` with connect and read timeouts, retrying with exponential
+/// backoff so that a flaky network does not hang or immediately fail the build.
+fn download(url: &str) -> Vec<u8> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_connect(Some(Duration::from_secs(10)))
+        .timeout_recv_response(Some(Duration::from_secs(30)))
+        .timeout_recv_body(Some(Duration::from_secs(120)))
+        .build()
+        .into();
+
+    let mut backoff = RETRY_BACKOFF;
+    for attempt in 1..DOWNLOAD_ATTEMPTS {
+        match fetch(&agent, url) {
+            Ok(data) => return data,
+            Err(e) => {
+                println!(
+                    "cargo:warning=Font download failed (attempt {attempt} of {DOWNLOAD_ATTEMPTS}): {e}; retrying in {}s",
+                    backoff.as_secs()
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+        }
+    }
+
+    fetch(&agent, url).expect("Failed to download font")
+}
+
+/// Fetches `url` once and reads the whole body.
+fn fetch(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, ureq::Error> {
+    agent.get(url).call()?.body_mut().read_to_vec()
+}
@@ -1,7 +1,109 @@
+/// A standard set of luma coefficients for converting RGB to YUV.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorSpace {
+    /// ITU-R BT.601, the standard-definition coefficients.
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, the high-definition coefficients.
+    Bt709,
+    /// ITU-R BT.2020, the ultra-high-definition (and HDR) coefficients.
+    Bt2020,
+}
+
+/// The red, green, and blue luma coefficients of each color space, in enum order.
+const LUMA_COEFFICIENTS: [[f64; 3]; 3] = [
+    [0.299, 0.587, 0.114],
+    [0.2126, 0.7152, 0.0722],
+    [0.2627, 0.6780, 0.0593],
+];
+
+impl ColorSpace {
+    /// Returns the red, green, and blue luma coefficients, which sum to 1.
+    pub fn coefficients(&self) -> [f64; 3] {
+        LUMA_COEFFICIENTS[*self as usize]
+    }
+}
+
+/// How the luminance of a source pixel is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Luma {
+    /// The YUV luma: a weighted sum of the gamma-encoded channels.
+    #[default]
+    Yuv,
+    /// The CIELAB lightness L*, which is perceptually more uniform in the midtones.
+    Lab,
+}
+
+impl Luma {
+    /// Returns the luminance of an RGBA color in [0, 1]; `weights` are the
+    /// channel weights of [`Luma::Yuv`] and are ignored by [`Luma::Lab`].
+    pub fn of(self, rgba: &[u8; 4], weights: [f64; 3]) -> f64 {
+        match self {
+            Luma::Yuv => Color::luminance_weighted(rgba, weights),
+            Luma::Lab => Color::lightness_lab(rgba),
+        }
+    }
+
+    /// Returns the luminance of a gamma-encoded gray level in [0, 1].
+    pub fn of_gray(self, gray: f64) -> f64 {
+        match self {
+            Luma::Yuv => gray,
+            Luma::Lab => Color::lightness_from_linear(Color::srgb_to_linear(gray)),
+        }
+    }
+}
+
+use crate::error::{Result, TypistError};
+
 /// A utility struct for color-related operations.
 pub struct Color {}
 
 impl Color {
+    /// The Rec. 601 luma weights of the red, green, and blue channels.
+    pub const REC601_WEIGHTS: [f64; 3] = LUMA_COEFFICIENTS[ColorSpace::Bt601 as usize];
+
+    /// Parses a hex color in the `#rgb`, `#rrggbb`, or `#rrggbbaa` form, with or
+    /// without the leading `#`, into RGBA. The alpha defaults to 255 (opaque).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use typistapp::color::Color;
+    ///
+    /// assert_eq!(Color::from_hex("#f80").unwrap(), [255, 136, 0, 255]);
+    /// assert_eq!(Color::from_hex("ff880080").unwrap(), [255, 136, 0, 128]);
+    /// ```
+    pub fn from_hex(s: &str) -> Result<[u8; 4]> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let invalid = || TypistError::InvalidColor(s.to_string());
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let digits: Vec<u8> = hex
+            .chars()
+            .filter_map(|c| c.to_digit(16))
+            .map(|d| d as u8)
+            .collect();
+
+        let mut rgba = [255; 4];
+        match digits.len() {
+            // each digit of the short form is doubled, so `f` becomes `ff`.
+            3 => {
+                for (channel, digit) in rgba.iter_mut().zip(&digits) {
+                    *channel = digit * 17;
+                }
+            }
+            6 | 8 => {
+                for (channel, pair) in rgba.iter_mut().zip(digits.chunks(2)) {
+                    *channel = pair[0] * 16 + pair[1];
+                }
+            }
+            _ => return Err(invalid()),
+        }
+
+        Ok(rgba)
+    }
+
     /// Calculates the luminance of an RGBA color.
     ///
     /// # Arguments
@@ -21,14 +123,86 @@ impl Color {
     /// assert!(luminance > 0.9);
     /// ```
     pub fn luminance_from_rgba(rgba: &[u8; 4]) -> f64 {
+        Self::luminance_from_rgba_in(rgba, ColorSpace::Bt601)
+    }
+
+    /// Calculates the luminance of an RGBA color like [`Color::luminance_from_rgba`],
+    /// using the coefficients of the given color space.
+    pub fn luminance_from_rgba_in(rgba: &[u8; 4], space: ColorSpace) -> f64 {
         let r = rgba[0] as f64 / 255.0;
         let g = rgba[1] as f64 / 255.0;
         let b = rgba[2] as f64 / 255.0;
 
-        let yuv = Self::convert_rgb_to_yuv(r, g, b);
+        let yuv = Self::convert_rgb_to_yuv_in(r, g, b, space);
         Self::luminance_from_yuv(&yuv)
     }
 
+    /// Calculates the luminance of an RGBA color as a weighted sum of its channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `rgba` - A reference to a 4-element array representing a color in RGBA format (0–255 range).
+    /// * `weights` - Non-negative weights of the red, green, and blue channels.
+    ///
+    /// # Returns
+    ///
+    /// * A `f64` value in the 0.0–1.0 range. The weights are normalized by their sum,
+    ///   so only their ratio matters; weights summing to zero yield 0.0.
+    pub fn luminance_weighted(rgba: &[u8; 4], weights: [f64; 3]) -> f64 {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let sum: f64 = rgba[..3]
+            .iter()
+            .zip(weights)
+            .map(|(&c, w)| c as f64 / 255.0 * w)
+            .sum();
+        (sum / total).clamp(0.0, 1.0)
+    }
+
+    /// Calculates the CIELAB lightness L* of an RGBA color, normalized from
+    /// 0–100 to the 0.0–1.0 range. The sRGB channels are linearized and converted
+    /// to the relative luminance Y of CIE XYZ (D65), the only XYZ component that
+    /// L* depends on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use typistapp::color::Color;
+    ///
+    /// let gray = Color::lightness_lab(&[128, 128, 128, 255]);
+    /// assert!((gray - 0.536).abs() < 0.001);
+    /// ```
+    pub fn lightness_lab(rgba: &[u8; 4]) -> f64 {
+        let [r, g, b] = [rgba[0], rgba[1], rgba[2]].map(|c| Self::srgb_to_linear(c as f64 / 255.0));
+        let [kr, kg, kb] = ColorSpace::Bt709.coefficients();
+        Self::lightness_from_linear(kr * r + kg * g + kb * b)
+    }
+
+    /// Converts a gamma-encoded sRGB channel in [0, 1] to linear light.
+    fn srgb_to_linear(c: f64) -> f64 {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    /// Converts a relative luminance Y in [0, 1] (with a white point of 1) to
+    /// the CIELAB lightness L*, normalized to [0, 1].
+    fn lightness_from_linear(y: f64) -> f64 {
+        // NOTE: below (6/29)^3 the cube root is replaced by a linear segment.
+        const DELTA: f64 = 6.0 / 29.0;
+        let f = if y > DELTA.powi(3) {
+            y.cbrt()
+        } else {
+            y / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        };
+        ((116.0 * f - 16.0) / 100.0).clamp(0.0, 1.0)
+    }
+
     /// Converts an RGB color to YUV color space.
     ///
     /// # Arguments
@@ -47,6 +221,21 @@ impl Color {
         [y, u, v]
     }
 
+    /// Converts an RGB color to YUV color space like [`Color::convert_rgb_to_yuv`],
+    /// using the coefficients of the given color space.
+    ///
+    /// # Returns
+    ///
+    /// * A `[f64; 3]` array of the Y, U, and V components, where U and V are
+    ///   scaled into the -0.5–0.5 range.
+    pub fn convert_rgb_to_yuv_in(r: f64, g: f64, b: f64, space: ColorSpace) -> [f64; 3] {
+        let [kr, kg, kb] = space.coefficients();
+        let y = kr * r + kg * g + kb * b;
+        let u = 0.5 * (b - y) / (1.0 - kb);
+        let v = 0.5 * (r - y) / (1.0 - kr);
+        [y, u, v]
+    }
+
     /// Extracts the luminance component (Y) from a YUV color.
     ///
     /// # Arguments
@@ -63,7 +252,77 @@ impl Color {
 
 #[cfg(test)]
 mod tests {
-    use super::Color;
+    use super::{Color, ColorSpace, Luma};
+    use crate::error::TypistError;
+
+    #[test]
+    fn lightness_lab_of_known_colors() {
+        let lab = |r, g, b| Color::lightness_lab(&[r, g, b, 255]);
+        assert!((lab(255, 255, 255) - 1.0).abs() < 1e-9);
+        assert_eq!(lab(0, 0, 0), 0.0);
+        assert!((lab(128, 128, 128) - 0.536).abs() < 0.001);
+        // pure green is far lighter than pure blue.
+        assert!((lab(0, 255, 0) - 0.877).abs() < 0.001);
+        assert!((lab(0, 0, 255) - 0.323).abs() < 0.001);
+        // L* lifts the dark midtones that the gamma-encoded luma leaves low.
+        assert!(lab(64, 64, 64) > Color::luminance_from_rgba(&[64, 64, 64, 255]));
+    }
+
+    #[test]
+    fn luma_lab_matches_for_gray_levels() {
+        for v in [0u8, 10, 64, 128, 200, 255] {
+            let gray = Luma::Lab.of_gray(v as f64 / 255.0);
+            assert!((gray - Luma::Lab.of(&[v, v, v, 255], [0.0; 3])).abs() < 1e-12);
+            assert_eq!(Luma::Yuv.of_gray(v as f64 / 255.0), v as f64 / 255.0);
+        }
+    }
+
+    #[test]
+    fn from_hex_parses_all_forms() {
+        assert_eq!(Color::from_hex("#ff8800").unwrap(), [255, 136, 0, 255]);
+        assert_eq!(Color::from_hex("ff8800").unwrap(), [255, 136, 0, 255]);
+        assert_eq!(Color::from_hex("#F80").unwrap(), [255, 136, 0, 255]);
+        assert_eq!(Color::from_hex("abc").unwrap(), [170, 187, 204, 255]);
+        assert_eq!(Color::from_hex("#00000000").unwrap(), [0, 0, 0, 0]);
+        assert_eq!(
+            Color::from_hex("12345678").unwrap(),
+            [0x12, 0x34, 0x56, 0x78]
+        );
+    }
+
+    #[test]
+    fn from_hex_rejects_invalid_length() {
+        for s in [
+            "",
+            "#",
+            "#f",
+            "#ff",
+            "#ffff",
+            "#fffff",
+            "#fffffff",
+            "#fffffffff",
+        ] {
+            assert!(
+                matches!(Color::from_hex(s), Err(TypistError::InvalidColor(ref input)) if input == s),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_hex_rejects_non_hex_characters() {
+        for s in [
+            "#gg0000",
+            "#12345z",
+            "#+12",
+            "##fff",
+            "#ｆｆｆ",
+            " #fff",
+            "#fff ",
+        ] {
+            assert!(Color::from_hex(s).is_err(), "{s:?}");
+        }
+    }
 
     #[test]
     fn luminance_black() {
@@ -111,6 +370,52 @@ mod tests {
         assert!((lum - 0.114).abs() < 0.01);
     }
 
+    #[test]
+    fn luminance_weighted_normalizes_weights() {
+        let rgba = [255, 0, 0, 255];
+        assert_eq!(Color::luminance_weighted(&rgba, [2.0, 1.0, 1.0]), 0.5);
+        assert_eq!(Color::luminance_weighted(&rgba, [1.0, 0.0, 0.0]), 1.0);
+        assert_eq!(Color::luminance_weighted(&rgba, [0.0, 0.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn luminance_weighted_rec601_matches_yuv() {
+        let rgba = [64, 128, 192, 255];
+        let weighted = Color::luminance_weighted(&rgba, Color::REC601_WEIGHTS);
+        assert!((weighted - Color::luminance_from_rgba(&rgba)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn luminance_of_primaries_per_color_space() {
+        for space in [ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020] {
+            let [kr, kg, kb] = space.coefficients();
+            let lum = |rgba| Color::luminance_from_rgba_in(&rgba, space);
+            assert!((lum([255, 0, 0, 255]) - kr).abs() < 1e-9);
+            assert!((lum([0, 255, 0, 255]) - kg).abs() < 1e-9);
+            assert!((lum([0, 0, 255, 255]) - kb).abs() < 1e-9);
+            assert!((lum([255, 255, 255, 255]) - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn convert_rgb_to_yuv_in_bt601_matches_fixed_matrix() {
+        let fixed = Color::convert_rgb_to_yuv(0.25, 0.5, 0.75);
+        let table = Color::convert_rgb_to_yuv_in(0.25, 0.5, 0.75, ColorSpace::Bt601);
+        for (a, b) in fixed.iter().zip(table) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn convert_rgb_to_yuv_in_chroma_extremes() {
+        for space in [ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020] {
+            let [_, u, _] = Color::convert_rgb_to_yuv_in(0.0, 0.0, 1.0, space);
+            let [_, _, v] = Color::convert_rgb_to_yuv_in(1.0, 0.0, 0.0, space);
+            assert!((u - 0.5).abs() < 1e-9);
+            assert!((v - 0.5).abs() < 1e-9);
+        }
+    }
+
     #[test]
     fn convert_rgb_to_yuv() {
         let yuv = Color::convert_rgb_to_yuv(1.0, 0.0, 0.0); // pure red
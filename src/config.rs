@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use image::GrayImage;
+
+use crate::color::{Color, Luma};
+use crate::element::{Align, Sample};
+use crate::metric::{Pearson, SimilarityMetric};
+use crate::model::{SortOrder, TileFilter};
+use crate::{FULL_WIDTH_SPACE, IMAGE_FONT_SIZE, IMAGE_MARGIN};
+
+/// Tunable parameters for converting an image into typist-art.
+///
+/// Tiles and glyphs meet at a common resolution of [`Config::cell_size`] squared
+/// (20×20 by default) values: the source is resized with [`Config::tile_filter`]
+/// so each tile spans that many pixels, while glyphs are rendered at
+/// [`Config::supersample`] times that size and box-downsampled to it.
+/// [`Config::match_resolution`] can then reduce both further before matching.
+///
+/// The defaults are used by [`Model::new`](crate::model::Model::new).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The strategy used to derive a tile's luminance from its pixels.
+    pub sample: Sample,
+
+    /// The pixel scale at which glyphs are rendered into their cells.
+    /// Defaults to the font size so glyphs fill the tile.
+    pub glyph_scale: f32,
+
+    /// The blank border in pixels around the font size on each side of a glyph cell.
+    /// Picture tiles grow and shrink with the cell, so a wider margin frames
+    /// glyphs more sparsely and a narrower one more densely.
+    pub glyph_margin: u32,
+
+    /// Normalizes picture luminance against the fixed [0, 1] range instead of
+    /// the image's own min/max, so separately converted images share one tone scale.
+    pub fixed_range: bool,
+
+    /// Normalizes each tile against the luminance range of the `window`×`window`
+    /// tiles around it rather than the whole image, which brings out detail in
+    /// both the bright and the dark parts of a mixed-lighting image. Takes
+    /// precedence over [`Config::fixed_range`]. `None` normalizes globally.
+    pub local_contrast: Option<u32>,
+
+    /// The order of the typeset before matching. Anything but luminance order
+    /// switches the candidate search to a slower linear scan.
+    pub sort: SortOrder,
+
+    /// Downsamples characteristics to this many values per side before matching,
+    /// trading a little accuracy for speed. `None` matches at full resolution.
+    pub match_resolution: Option<usize>,
+
+    /// Takes every glyph within this luminance distance of a tile as a candidate,
+    /// instead of a fixed number of tonal neighbors. `None` uses the fixed count.
+    pub luminance_band: Option<f64>,
+
+    /// The usage weight of typeset characters, such as one parsed by
+    /// [`parse_typeset`](crate::parse_typeset). Glyphs weighted below 1 are picked
+    /// less often and glyphs above 1 more often; unlisted characters weigh 1.
+    pub glyph_weights: HashMap<char, f64>,
+
+    /// Characters removed from the typeset before it is rendered, whatever
+    /// the typeset's source.
+    pub glyph_denylist: HashSet<char>,
+
+    /// Scores only the `k` glyphs tonally closest to a tile with the metric.
+    /// Candidates are ranked by their cheap luminance distance first, so this
+    /// bounds the cost of expensive metrics. With a luminance band, the band is
+    /// cut to its `k` closest glyphs. `None` scores the default 16 neighbors,
+    /// or the whole band.
+    pub metric_topk: Option<usize>,
+
+    /// Rounds the number of lines up instead of down, padding the partial bottom
+    /// tile with a white background so no content is cropped.
+    pub retain_ratio: bool,
+
+    /// The strength of the edge enhancement applied to the source before tiling,
+    /// which pushes the matcher toward denser glyphs along edges. 0 disables it.
+    pub outline: f32,
+
+    /// Renders glyphs at this multiple of the cell resolution before
+    /// downsampling, for smoother characteristics. 1 disables supersampling.
+    pub supersample: u32,
+
+    /// Spreads each glyph coverage sample over the pixels it overlaps at the
+    /// glyph's fractional position, instead of snapping it to one pixel.
+    /// Thin strokes alias less in the rendered characteristics.
+    pub subpixel_coverage: bool,
+
+    /// Inverts the tile luminance used for glyph matching, so dense glyphs
+    /// land on bright areas. The source image itself is left untouched.
+    pub invert_luminance: bool,
+
+    /// Pivots the tile luminance used for glyph matching around this tone,
+    /// folding both the darkest and the lightest areas toward the same side.
+    /// See [`Element::pivot`](crate::element::Element::pivot).
+    pub pivot: Option<f64>,
+
+    /// Fails the conversion if any tile finds no match, instead of leaving it blank.
+    pub strict: bool,
+
+    /// The weights of the red, green, and blue channels when computing the
+    /// luminance of the source. Defaults to the Rec. 601 luma weights.
+    pub channel_weights: [f64; 3],
+
+    /// How the luminance of source pixels is measured. [`Luma::Lab`] ignores
+    /// [`Config::channel_weights`].
+    pub luma: Luma,
+
+    /// Blends each source pixel toward white by its transparency, so
+    /// transparent regions map to sparse glyphs.
+    pub alpha_as_lightness: bool,
+
+    /// How glyphs are positioned within their cells when rendered.
+    pub align: Align,
+
+    /// The character placed in tiles without a match.
+    pub fill_char: char,
+
+    /// The filter used to resize the source to the tile grid. `Nearest` keeps
+    /// hard edges; `Lanczos3` smooths them.
+    pub tile_filter: TileFilter,
+
+    /// Resizes large sources in two stages: a fast area-averaging downscale to
+    /// twice the target size, then `tile_filter` to the target. This avoids the
+    /// aliasing of filtering a huge image in one go. Sources no larger than twice
+    /// the target in both dimensions are resized in one stage.
+    pub two_stage_resize: bool,
+
+    /// Keeps only one glyph of each set of case variants (such as `A` and `a`)
+    /// in the typeset, freeing candidate slots for distinct shapes.
+    pub collapse_case: bool,
+
+    /// Sorts the typeset with an unstable sort, which is faster for very large
+    /// typesets but may reorder glyphs of equal luminance (or ink), and so change
+    /// which of them the luminance search lands on.
+    pub unstable_sort: bool,
+
+    /// Advances each glyph drawn by [`Model::overlay`](crate::model::Model::overlay)
+    /// by its advance width in the font instead of a whole tile, for more natural
+    /// spacing with Latin typesets. Terminal output always stays monospaced.
+    pub proportional: bool,
+
+    /// Reuses the glyph chosen for a flat tile for every other flat tile of the
+    /// same quantized luminance, skipping their search. The first such tile in
+    /// row-major order is matched for all of them, so the output is deterministic.
+    /// Tiles with any structure are always matched in full. Trades exactness for
+    /// speed on flat images.
+    pub match_cache: bool,
+
+    /// A grayscale map of where the art should be most faithful, with the aspect
+    /// ratio of the source. It is resampled to one value per tile: tiles at or
+    /// above half brightness are scored with twice the default candidates
+    /// (a set `metric_topk` is kept as is), and the rest take the tonally
+    /// closest glyph without scoring alternatives.
+    /// `None` matches every tile alike.
+    pub importance_mask: Option<Arc<GrayImage>>,
+
+    /// Scores each candidate glyph against a tile. Defaults to Pearson correlation.
+    pub metric: Arc<dyn SimilarityMetric>,
+}
+
+impl Config {
+    /// Returns the side length in pixels of a glyph cell and of a picture tile:
+    /// the font size plus [`Config::glyph_margin`] on each side.
+    pub fn cell_size(&self) -> u32 {
+        IMAGE_FONT_SIZE + self.glyph_margin * 2
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            sample: Sample::default(),
+            glyph_scale: IMAGE_FONT_SIZE as f32,
+            glyph_margin: IMAGE_MARGIN,
+            fixed_range: false,
+            local_contrast: None,
+            sort: SortOrder::default(),
+            match_resolution: None,
+            luminance_band: None,
+            glyph_weights: HashMap::new(),
+            glyph_denylist: HashSet::new(),
+            metric_topk: None,
+            retain_ratio: false,
+            outline: 0.0,
+            supersample: 1,
+            subpixel_coverage: false,
+            invert_luminance: false,
+            pivot: None,
+            strict: false,
+            channel_weights: Color::REC601_WEIGHTS,
+            luma: Luma::default(),
+            alpha_as_lightness: false,
+            align: Align::default(),
+            fill_char: FULL_WIDTH_SPACE,
+            tile_filter: TileFilter::default(),
+            two_stage_resize: false,
+            collapse_case: false,
+            unstable_sort: false,
+            proportional: false,
+            match_cache: false,
+            importance_mask: None,
+            metric: Arc::new(Pearson),
+        }
+    }
+}
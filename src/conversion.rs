@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+
+use image::{GrayImage, Luma};
+
+/// The outcome of converting an image into typist-art.
+/// Holds the rendered lines along with the per-tile match scores.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionResult {
+    /// The typist-art, one string per row.
+    lines: Vec<String>,
+
+    /// The similarity score of the best match for each tile, in row-major order,
+    /// as computed by the configured metric, or `None` for tiles without a match.
+    scores: Vec<Option<f64>>,
+
+    /// The normalized luminance of the glyph matched to each tile, in row-major
+    /// order. Empty when the art was not produced by matching.
+    luminances: Vec<f64>,
+
+    /// The number of characters (columns) per line.
+    columns: u32,
+
+    /// The number of lines (rows).
+    rows: u32,
+}
+
+impl ConversionResult {
+    /// Constructs a new ConversionResult from its lines and per-tile scores.
+    pub fn new(lines: Vec<String>, scores: Vec<Option<f64>>, columns: u32, rows: u32) -> Self {
+        ConversionResult {
+            lines,
+            scores,
+            luminances: Vec::new(),
+            columns,
+            rows,
+        }
+    }
+
+    /// Attaches the luminance of the glyph matched to each tile, in row-major order.
+    pub fn with_luminances(mut self, luminances: Vec<f64>) -> Self {
+        self.luminances = luminances;
+        self
+    }
+
+    /// Returns the typist-art, one string per row.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Returns the similarity score of each tile in row-major order,
+    /// or `None` for tiles without a match.
+    pub fn scores(&self) -> &[Option<f64>] {
+        &self.scores
+    }
+
+    /// Returns the mean similarity score over the matched tiles, or `None` if
+    /// no tile has a score. Unmatched tiles are left out rather than given a
+    /// score, since no value is the worst for every metric.
+    pub fn mean_score(&self) -> Option<f64> {
+        let (sum, count) = self
+            .scores
+            .iter()
+            .flatten()
+            .fold((0.0, 0), |(sum, count), score| (sum + score, count + 1));
+        if count == 0 {
+            return None;
+        }
+
+        Some(sum / count as f64)
+    }
+
+    /// Returns the luminance of the glyph matched to each tile in row-major order.
+    pub fn luminances(&self) -> &[f64] {
+        &self.luminances
+    }
+
+    /// Returns the number of characters (columns) per line.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Returns the number of lines (rows).
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the characters of `typeset` that appear nowhere in the art,
+    /// in typeset order.
+    pub fn unused_characters(&self, typeset: &[char]) -> Vec<char> {
+        let used: HashSet<char> = self.lines.iter().flat_map(|line| line.chars()).collect();
+        let mut seen = HashSet::new();
+        typeset
+            .iter()
+            .copied()
+            .filter(|c| !used.contains(c) && seen.insert(*c))
+            .collect()
+    }
+
+    /// Surrounds the grid with `margin` rows and columns of `fill` cells on all
+    /// sides. The added cells have no score, like tiles without a match, and have
+    /// a luminance of 1.0 (blank paper).
+    pub fn with_margin(self, margin: u32, fill: char) -> Self {
+        if margin == 0 {
+            return self;
+        }
+
+        let columns = self.columns + margin * 2;
+        let rows = self.rows + margin * 2;
+        let blank: String = std::iter::repeat_n(fill, columns as usize).collect();
+        let side: String = std::iter::repeat_n(fill, margin as usize).collect();
+
+        let mut lines = vec![blank.clone(); margin as usize];
+        lines.extend(self.lines.iter().map(|line| format!("{side}{line}{side}")));
+        lines.extend(std::iter::repeat_n(blank, margin as usize));
+
+        let scores = self.pad(&self.scores, margin, None);
+        let luminances = if self.luminances.is_empty() {
+            Vec::new()
+        } else {
+            self.pad(&self.luminances, margin, 1.0)
+        };
+
+        ConversionResult::new(lines, scores, columns, rows).with_luminances(luminances)
+    }
+
+    /// Pads a row-major per-tile grid by `margin` cells of `outside` on all sides.
+    /// Missing values inside the original grid become `outside` as well.
+    fn pad<T: Copy>(&self, values: &[T], margin: u32, outside: T) -> Vec<T> {
+        let columns = self.columns + margin * 2;
+        let rows = self.rows + margin * 2;
+        (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let inside = (margin..margin + self.columns).contains(&x)
+                    && (margin..margin + self.rows).contains(&y);
+                if !inside {
+                    return outside;
+                }
+                let index = ((y - margin) * self.columns + (x - margin)) as usize;
+                values.get(index).copied().unwrap_or(outside)
+            })
+            .collect()
+    }
+
+    /// Encodes the matched luminance of each tile as a binary PGM (P5) image of
+    /// `columns`×`rows` pixels, with [0, 1] mapped onto 0–255.
+    /// Tiles without a luminance are black.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut pgm = format!("P5\n{} {}\n255\n", self.columns, self.rows).into_bytes();
+        pgm.extend((0..(self.columns * self.rows) as usize).map(|i| {
+            let luminance = self.luminances.get(i).copied().unwrap_or(0.0);
+            (luminance.clamp(0.0, 1.0) * 255.0).round() as u8
+        }));
+
+        pgm
+    }
+
+    /// Formats the grid as rows of space-separated `char:score` cells with scores
+    /// rounded to two decimals, for finding poorly matched regions.
+    /// Cells without a score show `-` instead.
+    pub fn debug_grid(&self) -> String {
+        let mut grid = String::new();
+        for (y, line) in self.lines.iter().enumerate() {
+            let cells: Vec<String> = line
+                .chars()
+                .enumerate()
+                .map(
+                    |(x, c)| match self.scores.get(y * self.columns as usize + x) {
+                        Some(Some(score)) => format!("{c}:{score:.2}"),
+                        _ => format!("{c}:-"),
+                    },
+                )
+                .collect();
+            grid.push_str(&cells.join(" "));
+            grid.push('\n');
+        }
+
+        grid
+    }
+
+    /// Returns a grayscale image with one pixel per tile, where bright pixels
+    /// indicate a good match. Scores in [-1.0, 1.0] are mapped onto 0–255, which
+    /// suits correlation-like metrics where higher is better. Tiles without a
+    /// score are black.
+    pub fn quality_heatmap(&self) -> GrayImage {
+        GrayImage::from_fn(self.columns, self.rows, |x, y| {
+            let index = (y * self.columns + x) as usize;
+            let score = self.scores.get(index).copied().flatten().unwrap_or(-1.0);
+            let value = ((score.clamp(-1.0, 1.0) + 1.0) / 2.0 * 255.0).round() as u8;
+            Luma([value])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_heatmap_maps_scores_to_gray() {
+        let result =
+            ConversionResult::new(vec![], vec![Some(1.0), None, Some(0.0), Some(2.0)], 2, 2);
+        let heatmap = result.quality_heatmap();
+        assert_eq!(heatmap.dimensions(), (2, 2));
+        assert_eq!(heatmap.get_pixel(0, 0).0, [255]);
+        assert_eq!(heatmap.get_pixel(1, 0).0, [0]);
+        assert_eq!(heatmap.get_pixel(0, 1).0, [128]);
+        assert_eq!(heatmap.get_pixel(1, 1).0, [255]);
+    }
+
+    #[test]
+    fn mean_score_averages_tiles() {
+        let result = ConversionResult::new(
+            vec![],
+            vec![Some(1.0), Some(-1.0), Some(0.5), Some(0.5)],
+            2,
+            2,
+        );
+        assert_eq!(result.mean_score(), Some(0.25));
+        assert_eq!(
+            ConversionResult::new(vec![], vec![], 0, 0).mean_score(),
+            None
+        );
+    }
+
+    #[test]
+    fn mean_score_leaves_out_unmatched_tiles() {
+        let result = ConversionResult::new(vec![], vec![Some(0.25), None, Some(0.75)], 3, 1);
+        assert_eq!(result.mean_score(), Some(0.5));
+        assert_eq!(
+            ConversionResult::new(vec![], vec![None, None], 2, 1).mean_score(),
+            None
+        );
+    }
+
+    #[test]
+    fn quality_heatmap_missing_scores_are_dark() {
+        let result = ConversionResult::new(vec![], vec![], 1, 1);
+        assert_eq!(result.quality_heatmap().get_pixel(0, 0).0, [0]);
+    }
+
+    #[test]
+    fn with_margin_pads_lines_and_scores() {
+        let result = ConversionResult::new(vec!["Ａ".to_string()], vec![Some(0.5)], 1, 1)
+            .with_margin(1, '　');
+        assert_eq!(result.lines(), ["　　　", "　Ａ　", "　　　"]);
+        assert_eq!((result.columns(), result.rows()), (3, 3));
+        let mut scores = vec![None; 9];
+        scores[4] = Some(0.5);
+        assert_eq!(result.scores(), scores);
+    }
+
+    #[test]
+    fn with_margin_pads_luminances_with_paper() {
+        let result = ConversionResult::new(vec!["Ａ".to_string()], vec![Some(0.5)], 1, 1)
+            .with_luminances(vec![0.25])
+            .with_margin(1, '　');
+        let mut luminances = vec![1.0; 9];
+        luminances[4] = 0.25;
+        assert_eq!(result.luminances(), luminances);
+    }
+
+    #[test]
+    fn to_pgm_writes_header_and_tiles() {
+        let result = ConversionResult::new(vec![], vec![], 3, 1).with_luminances(vec![0.0, 1.0]);
+        let mut expected = b"P5\n3 1\n255\n".to_vec();
+        expected.extend([0, 255, 0]);
+        assert_eq!(result.to_pgm(), expected);
+    }
+
+    #[test]
+    fn debug_grid_pairs_characters_with_scores() {
+        let result = ConversionResult::new(
+            vec!["ＡＢ".to_string(), "Ｃ".to_string()],
+            vec![Some(0.5), Some(-0.125), None],
+            2,
+            2,
+        );
+        assert_eq!(result.debug_grid(), "Ａ:0.50 Ｂ:-0.12\nＣ:-\n");
+        let braille = ConversionResult::new(vec!["⠁".to_string()], vec![], 1, 1);
+        assert_eq!(braille.debug_grid(), "⠁:-\n");
+    }
+
+    #[test]
+    fn with_margin_zero_is_unchanged() {
+        let result = ConversionResult::new(vec!["Ａ".to_string()], vec![Some(0.5)], 1, 1);
+        assert_eq!(result.clone().with_margin(0, '　'), result);
+    }
+
+    #[test]
+    fn unused_characters_lists_complement_in_order() {
+        let result =
+            ConversionResult::new(vec!["ＡＢ".to_string(), "ＢＡ".to_string()], vec![], 2, 2);
+        let typeset = ['Ｃ', 'Ａ', 'Ｄ', 'Ｂ', 'Ｃ'];
+        assert_eq!(result.unused_characters(&typeset), vec!['Ｃ', 'Ｄ']);
+    }
+}
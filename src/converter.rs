@@ -0,0 +1,296 @@
+use ab_glyph::FontArc;
+use image::{DynamicImage, RgbaImage};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::config::Config;
+use crate::conversion::ConversionResult;
+use crate::error::{Result, TypistError};
+use crate::model::Model;
+use crate::typesetter::Typesetter;
+
+/// A reusable entry point that owns the font, the typeset, and the configuration,
+/// so each conversion only needs an image and an output width.
+///
+/// # Example
+///
+/// ```no_run
+/// use typistapp::{FONT_DATA, config::Config, converter::Converter};
+///
+/// let converter = Converter::new(FONT_DATA, &['Ａ', 'ｏ', '。', '　'], Config::default())?;
+/// let image = image::open("cat.png")?;
+/// let result = converter.convert(&image, 64)?;
+/// for line in result.lines() {
+///     println!("{line}");
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Converter {
+    /// The font, the characters, and the configuration that every conversion
+    /// uses, with the typeset they render.
+    typesetter: Typesetter,
+}
+
+impl Converter {
+    /// Creates a converter, parsing the font and rendering the typeset once.
+    /// Fails if the font does not parse or the typeset is empty.
+    pub fn new(font: &[u8], characters: &[char], config: Config) -> Result<Self> {
+        if characters.is_empty() {
+            return Err(TypistError::EmptyTypeset);
+        }
+        Self::with_font(FontArc::try_from_vec(font.to_vec())?, characters, config)
+    }
+
+    /// Creates a converter from a parsed font, rendering the typeset once.
+    fn with_font(font: FontArc, characters: &[char], config: Config) -> Result<Self> {
+        let typesetter = Typesetter::new(characters, font, config);
+        typesetter.glyphs()?;
+
+        Ok(Converter { typesetter })
+    }
+
+    /// Returns the configuration used for every conversion.
+    pub fn config(&self) -> &Config {
+        self.typesetter.config()
+    }
+
+    /// Converts `image` into typist-art `length` characters wide.
+    pub fn convert(&self, image: &DynamicImage, length: u32) -> Result<ConversionResult> {
+        let (image, lines) = Model::prepare_image(image, length, self.config())?;
+        self.typesetter
+            .convert_with_typeset(&image, length, lines, self.typesetter.glyphs()?)
+    }
+
+    /// Converts each of `images` into typist-art `length` characters wide in
+    /// parallel, sharing the converter's typeset.
+    ///
+    /// The results are in the order of `images`, and an image that fails to
+    /// convert does not stop the others.
+    pub fn convert_many(
+        &self,
+        images: &[DynamicImage],
+        length: u32,
+    ) -> Vec<Result<ConversionResult>> {
+        images
+            .par_iter()
+            .map(|image| self.convert(image, length))
+            .collect()
+    }
+
+    /// Converts a raw RGBA buffer (`width * height * 4` bytes, row-major) into
+    /// typist-art `length` characters wide, so callers that decode images
+    /// themselves can skip `image::open`.
+    pub fn convert_rgba(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        length: u32,
+    ) -> Result<Vec<String>> {
+        let expected = width as usize * height as usize * 4;
+        let image = RgbaImage::from_raw(width, height, pixels.to_vec())
+            .filter(|_| pixels.len() == expected)
+            .ok_or(TypistError::InputSize {
+                actual: pixels.len(),
+                expected,
+            })?;
+
+        let result = self.convert(&DynamicImage::ImageRgba8(image), length)?;
+        Ok(result.lines().to_vec())
+    }
+
+    /// Converts a grid of luminance values in [0, 1] into typist-art,
+    /// bypassing image decoding.
+    ///
+    /// The grid is expected at pixel granularity: it holds
+    /// `(columns * cell) x (rows * cell)` values in row-major order, so each tile
+    /// covers `cell x cell` values, where `cell` is [`Config::cell_size`] (20 by default).
+    pub fn convert_from_luminance_grid(
+        &self,
+        grid: &[f64],
+        columns: u32,
+        rows: u32,
+    ) -> Result<ConversionResult> {
+        self.typesetter
+            .convert_from_luminance_grid(grid, columns, rows, self.typesetter.glyphs()?)
+    }
+}
+
+/// Creates a converter from font data, with the embedded typeset (see
+/// [`default_typeset`](crate::default_typeset)) and the default configuration.
+///
+/// This is the `TryFrom` conversion for font data. [`Model`] has none, since a
+/// model always holds the image it converts and cannot be built from a font alone.
+///
+/// # Example
+///
+/// ```no_run
+/// use typistapp::converter::Converter;
+///
+/// let converter = Converter::try_from(typistapp::FONT_DATA)?;
+/// let result = converter.convert(&image::open("cat.png")?, 64)?;
+/// assert!(Converter::try_from(&b"not a font"[..]).is_err());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+impl TryFrom<&[u8]> for Converter {
+    type Error = TypistError;
+
+    fn try_from(font: &[u8]) -> Result<Self> {
+        Self::new(font, &crate::default_typeset(), Config::default())
+    }
+}
+
+/// Creates a converter like the `TryFrom<&[u8]>` implementation, taking
+/// ownership of the font data instead of copying it.
+impl TryFrom<Vec<u8>> for Converter {
+    type Error = TypistError;
+
+    fn try_from(font: Vec<u8>) -> Result<Self> {
+        Self::with_font(
+            FontArc::try_from_vec(font)?,
+            &crate::default_typeset(),
+            Config::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn new_rejects_empty_typeset_and_invalid_font() {
+        assert!(matches!(
+            Converter::new(crate::TEST_FONT_DATA, &[], Config::default()),
+            Err(TypistError::EmptyTypeset)
+        ));
+        assert!(matches!(
+            Converter::new(&[0, 1, 2, 3], &['A'], Config::default()),
+            Err(TypistError::FontParse(_))
+        ));
+    }
+
+    #[test]
+    fn convert_builds_rows_of_the_requested_width() {
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &['M', 'A', '.'], Config::default()).unwrap();
+        let image = RgbaImage::from_fn(120, 60, |x, _| {
+            let v = (x * 2) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let result = converter
+            .convert(&DynamicImage::ImageRgba8(image), 3)
+            .unwrap();
+        assert_eq!((result.columns(), result.rows()), (3, 1));
+        assert_eq!(result.lines()[0].chars().count(), 3);
+    }
+
+    #[test]
+    fn convert_matches_a_model_with_the_same_settings() {
+        let characters = ['M', 'A', '.', 'W'];
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &characters, Config::default()).unwrap();
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 80, |x, y| {
+            let v = ((x + y) * 255 / 240) as u8;
+            Rgba([v, v, v, 255])
+        }));
+
+        let mut model = Model::new(8, &image, &characters, crate::TEST_FONT_DATA).unwrap();
+        let expected = model.convert().unwrap();
+        for _ in 0..2 {
+            assert_eq!(converter.convert(&image, 8).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn convert_from_luminance_grid_needs_no_image() {
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &['M', 'A', '.'], Config::default()).unwrap();
+        let size = Config::default().cell_size() as usize;
+        let grid: Vec<f64> = (0..2 * size * size)
+            .map(|i| (i % (2 * size)) as f64 / (2 * size) as f64)
+            .collect();
+
+        let result = converter.convert_from_luminance_grid(&grid, 2, 1).unwrap();
+        assert_eq!((result.columns(), result.rows()), (2, 1));
+        assert_eq!(result.lines()[0].chars().count(), 2);
+    }
+
+    #[test]
+    fn convert_many_keeps_input_order_and_errors() {
+        let gradient = |width, height, flip: bool| {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+                let x = if flip { width - 1 - x } else { x };
+                let v = (x * 255 / (width - 1)) as u8;
+                Rgba([v, v, v, 255])
+            }))
+        };
+        let images = [
+            gradient(80, 40, false),
+            DynamicImage::new_rgb8(0, 0),
+            gradient(80, 40, true),
+            gradient(120, 120, false),
+        ];
+        let characters = ['A', 'M', '.', 'W'];
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &characters, Config::default()).unwrap();
+        let results = converter.convert_many(&images, 4);
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[1], Err(TypistError::EmptyImage)));
+
+        for i in [0, 2, 3] {
+            let mut single = Model::new(4, &images[i], &characters, crate::TEST_FONT_DATA).unwrap();
+            let expected = single.convert().unwrap();
+            assert_eq!(results[i].as_ref().unwrap(), &expected, "{i}");
+        }
+        assert_eq!(results[3].as_ref().unwrap().rows(), 4);
+
+        assert!(converter.convert_many(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn convert_rgba_rejects_wrong_buffer_length() {
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &['A', 'M'], Config::default()).unwrap();
+        let result = converter.convert_rgba(&[0; 10], 2, 2, 1);
+        assert!(matches!(
+            result,
+            Err(TypistError::InputSize {
+                actual: 10,
+                expected: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn convert_rgba_builds_rows() {
+        let converter =
+            Converter::new(crate::TEST_FONT_DATA, &['A', 'M'], Config::default()).unwrap();
+        let size = Config::default().cell_size();
+        // 3 tiles wide and 2 tall, half black and half white.
+        let (width, height) = (size * 3, size * 2);
+        let pixels: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let v = if i % width < width / 2 { 0 } else { 255 };
+                [v, v, v, 255]
+            })
+            .collect();
+        let lines = converter.convert_rgba(&pixels, width, height, 3).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.chars().count() == 3));
+    }
+
+    #[test]
+    fn try_from_font_data_rejects_invalid_fonts() {
+        assert!(matches!(
+            Converter::try_from(&[0u8; 4][..]),
+            Err(TypistError::FontParse(_))
+        ));
+        assert!(matches!(
+            Converter::try_from(vec![0u8; 4]),
+            Err(TypistError::FontParse(_))
+        ));
+    }
+}
@@ -2,17 +2,116 @@ use log;
 
 use crate::F64_ALMOST_ZERO;
 
+/// The tolerance below which [`correlation`] treats the spread of its inputs as
+/// zero.
+pub const DEFAULT_EPSILON: f64 = F64_ALMOST_ZERO;
+
+/// The number of values the `simd` feature processes per vector.
+/// Four `f64` lanes fill a 256-bit vector register.
+#[cfg(feature = "simd")]
+const LANES: usize = 4;
+
 /// Computes the Pearson correlation coefficient between two vectors of f64 values.
-/// Returns None if the input lengths do not match or are empty.
+/// Returns None if the input lengths do not match, are empty, or contain
+/// non-finite values, which would otherwise poison every comparison of the result.
+///
+/// With the `simd` feature, the sums are accumulated in vectors of four lanes
+/// with the `wide` crate; inputs shorter than that take the scalar path.
+/// Both agree up to floating-point rounding.
 pub fn correlation(x_values: &[f64], y_values: &[f64]) -> Option<f64> {
+    correlation_with_epsilon(x_values, y_values, DEFAULT_EPSILON)
+}
+
+/// Computes the Pearson correlation coefficient like [`correlation`], treating
+/// spreads and mean differences below `epsilon` as zero instead of below
+/// [`DEFAULT_EPSILON`]. Inputs scaled far below 1 need a smaller tolerance, and
+/// noisy inputs a larger one to count as flat.
+///
+/// When both inputs are flat, they correlate perfectly (1.0) if their means are
+/// equal and not at all (0.0) otherwise; when only one is, the result is 0.0.
+pub fn correlation_with_epsilon(x_values: &[f64], y_values: &[f64], epsilon: f64) -> Option<f64> {
+    #[cfg(feature = "simd")]
+    if x_values.len() >= LANES {
+        return correlation_with(
+            x_values,
+            y_values,
+            epsilon,
+            simd::sums,
+            simd::centered_products,
+        );
+    }
+
+    correlation_with(x_values, y_values, epsilon, sums, centered_products)
+}
+
+/// Computes the Pearson correlation coefficient like [`correlation`], always on
+/// the scalar path, as a reference for the vectorized one.
+#[cfg(feature = "simd")]
+pub fn correlation_scalar(x_values: &[f64], y_values: &[f64]) -> Option<f64> {
+    correlation_with(x_values, y_values, DEFAULT_EPSILON, sums, centered_products)
+}
+
+/// Returns the sums of the x and y values.
+type SumsKernel = fn(&[f64], &[f64]) -> (f64, f64);
+
+/// Returns the sum of the products of the x and y values centered on the given
+/// means, and the sums of the squares of each.
+type ProductsKernel = fn(&[f64], &[f64], f64, f64) -> (f64, f64, f64);
+
+/// Computes the Pearson correlation coefficient with the given tolerance and
+/// kernels for the sums of the values and the sums of the centered products.
+fn correlation_with(
+    x_values: &[f64],
+    y_values: &[f64],
+    epsilon: f64,
+    sums: SumsKernel,
+    centered_products: ProductsKernel,
+) -> Option<f64> {
     if x_values.len() != y_values.len() || x_values.is_empty() || y_values.is_empty() {
         return None;
     }
+    if !x_values.iter().chain(y_values).all(|v| v.is_finite()) {
+        log::debug!("Correlation input contains non-finite values");
+        return None;
+    }
 
     let n = x_values.len();
-    let mean_x = x_values.iter().sum::<f64>() / n as f64;
-    let mean_y = y_values.iter().sum::<f64>() / n as f64;
+    let (sum_x, sum_y) = sums(x_values, y_values);
+    let mean_x = sum_x / n as f64;
+    let mean_y = sum_y / n as f64;
+
+    let (numerator, den_x, den_y) = centered_products(x_values, y_values, mean_x, mean_y);
+
+    let denominator = den_x.sqrt() * den_y.sqrt();
+    if denominator.abs() < epsilon {
+        let is_den_x_zero = den_x.abs() < epsilon;
+        let is_den_y_zero = den_y.abs() < epsilon;
+        let are_means_equal = (mean_x - mean_y).abs() < epsilon;
+
+        return match (is_den_x_zero, is_den_y_zero, are_means_equal) {
+            (true, true, true) => Some(1.0),
+            _ => Some(0.0),
+        };
+    }
+
+    let result = numerator / denominator;
+    log::trace!("Correlation result: {result}");
+    Some(result)
+}
+
+/// Returns the sums of the x and y values.
+fn sums(x_values: &[f64], y_values: &[f64]) -> (f64, f64) {
+    (x_values.iter().sum(), y_values.iter().sum())
+}
 
+/// Returns the sum of the products of the centered x and y values,
+/// and the sums of the squares of each.
+fn centered_products(
+    x_values: &[f64],
+    y_values: &[f64],
+    mean_x: f64,
+    mean_y: f64,
+) -> (f64, f64, f64) {
     let mut numerator = 0.0;
     let mut den_x = 0.0;
     let mut den_y = 0.0;
@@ -25,21 +124,76 @@ pub fn correlation(x_values: &[f64], y_values: &[f64]) -> Option<f64> {
         den_y += diff_y * diff_y;
     }
 
-    let denominator = den_x.sqrt() * den_y.sqrt();
-    if denominator.abs() < F64_ALMOST_ZERO {
-        let is_den_x_zero = den_x.abs() < F64_ALMOST_ZERO;
-        let is_den_y_zero = den_y.abs() < F64_ALMOST_ZERO;
-        let are_means_equal = (mean_x - mean_y).abs() < F64_ALMOST_ZERO;
+    (numerator, den_x, den_y)
+}
 
-        return match (is_den_x_zero, is_den_y_zero, are_means_equal) {
-            (true, true, true) => Some(1.0),
-            _ => Some(0.0),
-        };
+/// The vectorized kernels. `std::simd` is not stable yet, so the sums are kept
+/// in `wide` vectors of `LANES` values, which map onto the target's packed
+/// instructions (SSE2 or AVX on x86, NEON on AArch64) with a scalar fallback.
+#[cfg(feature = "simd")]
+mod simd {
+    use wide::f64x4;
+
+    use super::LANES;
+
+    /// Returns the chunks of `values` as vectors, and the values left over.
+    fn chunks(values: &[f64]) -> (impl Iterator<Item = f64x4> + '_, &[f64]) {
+        let chunks = values.chunks_exact(LANES);
+        let remainder = chunks.remainder();
+        (
+            chunks.map(|c| {
+                f64x4::from(<[f64; LANES]>::try_from(c).expect("chunks have LANES values"))
+            }),
+            remainder,
+        )
     }
 
-    let result = numerator / denominator;
-    log::trace!("Correlation result: {result}");
-    Some(result)
+    /// Like [`super::sums`].
+    pub(super) fn sums(x_values: &[f64], y_values: &[f64]) -> (f64, f64) {
+        let (x_chunks, x_rest) = chunks(x_values);
+        let (y_chunks, y_rest) = chunks(y_values);
+
+        let mut sum_x = f64x4::ZERO;
+        let mut sum_y = f64x4::ZERO;
+        for (x, y) in x_chunks.zip(y_chunks) {
+            sum_x += x;
+            sum_y += y;
+        }
+        let (rest_x, rest_y) = super::sums(x_rest, y_rest);
+
+        (sum_x.reduce_add() + rest_x, sum_y.reduce_add() + rest_y)
+    }
+
+    /// Like [`super::centered_products`].
+    pub(super) fn centered_products(
+        x_values: &[f64],
+        y_values: &[f64],
+        mean_x: f64,
+        mean_y: f64,
+    ) -> (f64, f64, f64) {
+        let (x_chunks, x_rest) = chunks(x_values);
+        let (y_chunks, y_rest) = chunks(y_values);
+        let (mean_x_lanes, mean_y_lanes) = (f64x4::splat(mean_x), f64x4::splat(mean_y));
+
+        let mut numerator = f64x4::ZERO;
+        let mut den_x = f64x4::ZERO;
+        let mut den_y = f64x4::ZERO;
+        for (x, y) in x_chunks.zip(y_chunks) {
+            let diff_x = x - mean_x_lanes;
+            let diff_y = y - mean_y_lanes;
+            numerator += diff_x * diff_y;
+            den_x += diff_x * diff_x;
+            den_y += diff_y * diff_y;
+        }
+        let (rest_numerator, rest_x, rest_y) =
+            super::centered_products(x_rest, y_rest, mean_x, mean_y);
+
+        (
+            numerator.reduce_add() + rest_numerator,
+            den_x.reduce_add() + rest_x,
+            den_y.reduce_add() + rest_y,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -56,6 +210,15 @@ mod tests {
         assert_eq!(correlation(&[], &[]), None);
     }
 
+    #[test]
+    fn correlation_non_finite_values_return_none() {
+        assert_eq!(correlation(&[1.0, f64::NAN, 3.0], &[1.0, 2.0, 3.0]), None);
+        assert_eq!(
+            correlation(&[1.0, 2.0, 3.0], &[f64::INFINITY, 2.0, 3.0]),
+            None
+        );
+    }
+
     #[test]
     fn correlation_valid_data_returns_some() {
         let x_values = [1.0, 2.0, 3.0];
@@ -64,4 +227,44 @@ mod tests {
         assert!(result.is_some());
         assert!((result.unwrap() - 1.0).abs() < 1e-9);
     }
+
+    #[test]
+    fn epsilon_decides_when_inputs_count_as_flat() {
+        // both spreads and the product of their roots are 2e-6; the means are equal.
+        let x_values = [0.0, 2e-3];
+        let y_values = [2e-3, 0.0];
+        let result = |epsilon| correlation_with_epsilon(&x_values, &y_values, epsilon).unwrap();
+        assert!((result(1.9e-6) + 1.0).abs() < 1e-9);
+        assert_eq!(result(2.1e-6), 1.0);
+        assert_eq!(
+            correlation(&x_values, &y_values),
+            Some(result(DEFAULT_EPSILON))
+        );
+
+        // with different means, flat inputs do not correlate.
+        let y_values = [1.0, 1.0 + 2e-3];
+        let result = correlation_with_epsilon(&[0.0, 2e-3], &y_values, 2.1e-6).unwrap();
+        assert_eq!(result, 0.0);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_matches_scalar() {
+        let mut state = 7;
+        let mut values = |n: usize| -> Vec<f64> {
+            (0..n)
+                .map(|_| (crate::split_mix64(&mut state) >> 11) as f64 / (1u64 << 53) as f64)
+                .collect()
+        };
+        let size = (crate::IMAGE_SIZE * crate::IMAGE_SIZE) as usize;
+        // the common tile size, lengths with a remainder, and a single chunk.
+        for n in [size, size + 3, 7, LANES] {
+            let (x, y) = (values(n), values(n));
+            let simd = correlation(&x, &y).unwrap();
+            let scalar = correlation_scalar(&x, &y).unwrap();
+            assert!((simd - scalar).abs() < 1e-12, "{n}: {simd} vs {scalar}");
+        }
+        let flat = vec![0.5; size];
+        assert_eq!(correlation(&flat, &flat), Some(1.0));
+    }
 }
@@ -1,19 +1,161 @@
-use ab_glyph::{Font, FontArc, PxScale};
-use anyhow::{Result, anyhow};
-use image::{DynamicImage, GenericImageView};
+use ab_glyph::{Font, FontArc, GlyphImageFormat, PxScale, ScaleFont};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage, imageops};
 use log;
 
-use crate::color::Color;
-use crate::{F64_ALMOST_ZERO, FULL_WIDTH_SPACE, IMAGE_SIZE};
+use crate::color::{Color, Luma};
+use crate::config::Config;
+use crate::error::{Result, TypistError};
+use crate::{F64_ALMOST_ZERO, FULL_WIDTH_SPACE, IMAGE_FONT_SIZE, IMAGE_SIZE, NUM_OF_DOMINANT_BINS};
+
+/// The strategy used to reduce a tile's pixel luminances to a single value.
+///
+/// `Average` is the fastest. `Median` needs a per-tile sort but is robust to
+/// outliers, and `Dominant` builds a small histogram to keep the most common
+/// tone, which preserves sharp features at the cost of some smoothness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sample {
+    /// The mean of all pixel luminances.
+    #[default]
+    Average,
+    /// The median of all pixel luminances.
+    Median,
+    /// The mean luminance of the most populated histogram bin.
+    Dominant,
+}
+
+/// How a glyph is positioned within its cell when rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Align {
+    /// Centers the glyph's own bounding box in the cell.
+    #[default]
+    Bbox,
+    /// Centers the font's line box (ascent to descent) and the glyph's advance
+    /// width, keeping every glyph on a shared baseline.
+    Baseline,
+}
+
+/// How [`Element::from_char`] renders a glyph into its cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphOptions {
+    /// The pixel scale at which the glyph is rendered.
+    pub scale: PxScale,
+
+    /// Renders the glyph at this multiple of the cell resolution and
+    /// box-downsamples it to the cell, so thin strokes produce fractional
+    /// coverage instead of aliased pixels. 1 disables supersampling.
+    pub supersample: u32,
+
+    /// How the glyph is positioned within its cell.
+    pub align: Align,
+
+    /// The side of the square cell in pixels.
+    pub cell: u32,
+
+    /// Spreads each coverage sample over the pixels it overlaps at the glyph's
+    /// fractional position, weighted by area, instead of snapping it to one pixel.
+    pub subpixel: bool,
+}
+
+impl Default for GlyphOptions {
+    fn default() -> Self {
+        GlyphOptions {
+            scale: PxScale::from(IMAGE_FONT_SIZE as f32),
+            supersample: 1,
+            align: Align::Bbox,
+            cell: IMAGE_SIZE,
+            subpixel: false,
+        }
+    }
+}
+
+impl From<&Config> for GlyphOptions {
+    /// Takes the glyph scale, supersampling, alignment, cell size, and subpixel
+    /// coverage of a conversion.
+    fn from(config: &Config) -> Self {
+        GlyphOptions {
+            scale: PxScale::from(config.glyph_scale),
+            supersample: config.supersample,
+            align: config.align,
+            cell: config.cell_size(),
+            subpixel: config.subpixel_coverage,
+        }
+    }
+}
+
+impl Sample {
+    /// Reduces the given pixel luminances to a single value.
+    /// Returns 0.0 for an empty slice.
+    pub fn luminance(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+
+        match self {
+            Sample::Average => values.iter().sum::<f64>() / values.len() as f64,
+            Sample::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[mid - 1] + sorted[mid]) / 2.0
+                } else {
+                    sorted[mid]
+                }
+            }
+            Sample::Dominant => {
+                let mut counts = [0usize; NUM_OF_DOMINANT_BINS];
+                let mut sums = [0.0; NUM_OF_DOMINANT_BINS];
+                for v in values {
+                    let bin = ((v.clamp(0.0, 1.0) * NUM_OF_DOMINANT_BINS as f64) as usize)
+                        .min(NUM_OF_DOMINANT_BINS - 1);
+                    counts[bin] += 1;
+                    sums[bin] += v;
+                }
+                // NOTE: ties resolve to the darkest bin.
+                let (bin, count) = counts
+                    .iter()
+                    .enumerate()
+                    .fold((0, 0), |acc, (i, &c)| if c > acc.1 { (i, c) } else { acc });
+                sums[bin] / count as f64
+            }
+        }
+    }
+}
+
+/// Measures how much ink a glyph puts into its cell, without building a `Model`.
+///
+/// Returns the mean coverage of the rendered cell in [0.0, 1.0], where 0.0 means
+/// no ink. Note this runs opposite to [`Element::luminance`], where 1.0 is blank.
+/// Glyphs without an outline, such as spaces, return 0.0.
+pub fn glyph_ink(font: &FontArc, character: char, scale: PxScale) -> Result<f32> {
+    let glyph = font.glyph_id(character).with_scale(scale);
+    if font.outline_glyph(glyph).is_none() {
+        return Ok(0.0);
+    }
+
+    let options = GlyphOptions {
+        scale,
+        ..Default::default()
+    };
+    let element = Element::from_char(font, character, &options)?;
+    Ok((1.0 - element.luminance()) as f32)
+}
 
 /// Represents either a character or image tile, along with its
 /// luminance and pixel characteristics used for comparison and matching.
+///
+/// With the `serde` feature, elements can be serialized; the tile image is skipped.
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Element {
     characteristics: Vec<f64>,
     luminance: f64,
     character: Option<char>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     image: Option<DynamicImage>,
+    // NOTE: stored as a logarithm so the derived default, 0, is a weight of 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    log_weight: f64,
 }
 
 impl Element {
@@ -29,7 +171,28 @@ impl Element {
             luminance,
             character,
             image,
+            log_weight: 0.0,
+        }
+    }
+
+    /// Sets the usage weight of the element, a positive multiplier of how readily
+    /// the matcher picks it: below 1 it is picked less often, above 1 more often.
+    /// Non-positive or non-finite weights are ignored.
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        if weight.is_finite() && weight > 0.0 {
+            self.log_weight = weight.ln();
         }
+        self
+    }
+
+    /// Returns the usage weight of the element, 1 unless set with [`Element::with_weight`].
+    pub fn weight(&self) -> f64 {
+        self.log_weight.exp()
+    }
+
+    /// Returns the natural logarithm of the usage weight, 0 for the default weight.
+    pub(crate) fn log_weight(&self) -> f64 {
+        self.log_weight
     }
 
     /// Returns the pixel intensity values of the element.
@@ -37,27 +200,106 @@ impl Element {
         &self.characteristics
     }
 
+    /// Returns the pixel intensity values of the element as rows of a square grid,
+    /// from top to bottom.
+    pub fn characteristics_2d(&self) -> impl Iterator<Item = &[f64]> {
+        self.characteristics.chunks(self.width().max(1))
+    }
+
+    /// Returns the width of the element's pixel grid.
+    ///
+    /// Elements are always square tiles, so this is the square root of the
+    /// number of characteristics.
+    pub fn width(&self) -> usize {
+        self.characteristics.len().isqrt()
+    }
+
+    /// Returns the height of the element's pixel grid.
+    pub fn height(&self) -> usize {
+        match self.width() {
+            0 => 0,
+            w => self.characteristics.len() / w,
+        }
+    }
+
+    /// Box-downsamples the element's pixel grid to `target`×`target` values.
+    /// Returns the characteristics unchanged if the grid is not larger than `target`.
+    pub fn downsample(&self, target: usize) -> Vec<f64> {
+        let (width, height) = (self.width(), self.height());
+        if target == 0 || (width <= target && height <= target) {
+            return self.characteristics.clone();
+        }
+
+        let mut result = Vec::with_capacity(target * target);
+        for oy in 0..target {
+            let (y0, y1) = (
+                oy * height / target,
+                ((oy + 1) * height / target).max(oy * height / target + 1),
+            );
+            for ox in 0..target {
+                let (x0, x1) = (
+                    ox * width / target,
+                    ((ox + 1) * width / target).max(ox * width / target + 1),
+                );
+                let mut sum = 0.0;
+                for y in y0..y1 {
+                    sum += self.characteristics[y * width + x0..y * width + x1]
+                        .iter()
+                        .sum::<f64>();
+                }
+                result.push(sum / ((y1 - y0) * (x1 - x0)) as f64);
+            }
+        }
+
+        result
+    }
+
     /// Returns the average luminance of the element.
     pub fn luminance(&self) -> f64 {
         self.luminance
     }
 
+    /// Returns the luminance as a 0–255 gray level, assuming it is normalized
+    /// to [0, 1]. Out-of-range values are clamped.
+    pub fn luminance_u8(&self) -> u8 {
+        (self.luminance.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
     /// Returns the character associated with this element, if any.
     pub fn character(&self) -> Option<char> {
         self.character
     }
 
+    /// Whether this is the placeholder element, [`Element::default`], which stands
+    /// in for tiles that found no match: it has neither a character nor
+    /// characteristics. Picture tiles have characteristics, so they never are.
+    pub fn is_placeholder(&self) -> bool {
+        self.character.is_none() && self.characteristics.is_empty()
+    }
+
     /// Returns a reference to the image of this element, if available.
     #[allow(dead_code)]
     pub fn image(&self) -> Option<&DynamicImage> {
         self.image.as_ref()
     }
 
-    /// Creates an element by rendering a character into an image using the provided font and scale,
-    /// then converting it into luminance data.
-    pub fn from_char(font: &FontArc, character: char, scale: PxScale) -> Result<Self> {
-        let (width, height) = (IMAGE_SIZE, IMAGE_SIZE);
+    /// Creates an element by rendering a character into a cell using the provided font
+    /// and `options`, then converting it into luminance data.
+    pub fn from_char(font: &FontArc, character: char, options: &GlyphOptions) -> Result<Self> {
+        let GlyphOptions {
+            scale,
+            supersample: factor,
+            align,
+            cell,
+            subpixel,
+        } = *options;
+        let factor = factor.max(1);
+        let (width, height) = (cell * factor, cell * factor);
         let mut characteristics = vec![1.0; (width * height) as usize];
+        let scale = PxScale {
+            x: scale.x * factor as f32,
+            y: scale.y * factor as f32,
+        };
 
         let glyph = font.glyph_id(character).with_scale(scale);
         let outline = match font.outline_glyph(glyph) {
@@ -65,83 +307,230 @@ impl Element {
             None => {
                 if character == FULL_WIDTH_SPACE {
                     return Ok(Element {
-                        characteristics,
+                        characteristics: vec![1.0; (cell * cell) as usize],
                         luminance: 1.0,
                         character: Some('　'),
                         image: None,
+                        log_weight: 0.0,
                     });
                 }
-                return Err(anyhow!(
-                    "Failed to outline glyph for character: {}",
-                    character
-                ));
+                return Self::from_raster_glyph(font, character, scale, factor, cell)
+                    .ok_or(TypistError::GlyphOutline(character));
             }
         };
 
         let bounds = outline.px_bounds();
 
-        // canvas center - glyph center
-        let glyph_center_x = bounds.min.x + bounds.width() / 2.0;
-        let glyph_center_y = bounds.min.y + bounds.height() / 2.0;
-
         let canvas_center_x = width as f32 / 2.0;
         let canvas_center_y = height as f32 / 2.0;
 
-        let offset_x = canvas_center_x - glyph_center_x;
-        let offset_y = canvas_center_y - glyph_center_y;
-
-        outline.draw(|x, y, c| {
-            let canvas_x = x as f32 + offset_x;
-            let canvas_y = y as f32 + offset_y;
+        // NOTE: `draw` yields coordinates relative to the glyph bounds, so the
+        // offsets place the bounds origin on the canvas.
+        let (offset_x, offset_y) = match align {
+            // canvas center - glyph center
+            Align::Bbox => (
+                canvas_center_x - bounds.width() / 2.0,
+                canvas_center_y - bounds.height() / 2.0,
+            ),
+            // the baseline sits where the centered line box puts it, and the bounds
+            // are relative to the glyph origin on that baseline.
+            Align::Baseline => {
+                let scaled = font.as_scaled(scale);
+                let origin_x = canvas_center_x - scaled.h_advance(font.glyph_id(character)) / 2.0;
+                let baseline_y = canvas_center_y + (scaled.ascent() + scaled.descent()) / 2.0;
+                (origin_x + bounds.min.x, baseline_y + bounds.min.y)
+            }
+        };
 
-            if canvas_x >= 0.0
-                && canvas_x < width as f32
-                && canvas_y >= 0.0
-                && canvas_y < height as f32
-            {
-                let index = (canvas_y as u32 * width + canvas_x as u32) as usize;
-                characteristics[index] = 1.0 - (c as f64);
+        // NOTE: with `subpixel`, each coverage sample is spread over the (up to four)
+        // canvas pixels its square overlaps at the glyph's fractional offset, weighted
+        // by the overlapping area, and accumulated. Otherwise it is written into the
+        // single pixel its corner falls in, dropping the fractional part of the offset.
+        if subpixel {
+            let mut ink = vec![0.0; characteristics.len()];
+            outline.draw(|x, y, c| {
+                let canvas_x = x as f32 + offset_x;
+                let canvas_y = y as f32 + offset_y;
+                let (left, top) = (canvas_x.floor(), canvas_y.floor());
+                let (fx, fy) = ((canvas_x - left) as f64, (canvas_y - top) as f64);
+                let shares = [
+                    (0, 0, (1.0 - fx) * (1.0 - fy)),
+                    (1, 0, fx * (1.0 - fy)),
+                    (0, 1, (1.0 - fx) * fy),
+                    (1, 1, fx * fy),
+                ];
+                for (dx, dy, area) in shares {
+                    let (px, py) = (left as i64 + dx, top as i64 + dy);
+                    if (0..width as i64).contains(&px) && (0..height as i64).contains(&py) {
+                        ink[(py as u32 * width + px as u32) as usize] += c as f64 * area;
+                    }
+                }
+            });
+            for (value, ink) in characteristics.iter_mut().zip(ink) {
+                *value = 1.0 - ink.min(1.0);
             }
-        });
+        } else {
+            outline.draw(|x, y, c| {
+                let canvas_x = x as f32 + offset_x;
+                let canvas_y = y as f32 + offset_y;
 
-        let luminance = characteristics.iter().sum::<f64>() / (width * height) as f64;
+                if canvas_x >= 0.0
+                    && canvas_x < width as f32
+                    && canvas_y >= 0.0
+                    && canvas_y < height as f32
+                {
+                    let index = (canvas_y as u32 * width + canvas_x as u32) as usize;
+                    characteristics[index] = 1.0 - (c as f64);
+                }
+            });
+        }
+
+        let mut element = Element {
+            characteristics,
+            luminance: 0.0,
+            character: Some(character),
+            image: None,
+            log_weight: 0.0,
+        };
+        if factor > 1 {
+            element.characteristics = element.downsample(cell as usize);
+        }
+        element.luminance =
+            element.characteristics.iter().sum::<f64>() / element.characteristics.len() as f64;
 
         log::debug!(
-            "Character: '{character}', Width: {width}, Height: {height}, Luminance: {luminance}"
+            "Character: '{character}', Width: {width}, Height: {height}, Supersample: {factor}, Luminance: {}",
+            element.luminance
         );
 
-        Ok(Element {
-            characteristics,
-            luminance,
+        Ok(element)
+    }
+
+    /// Renders a glyph without an outline from the PNG image embedded in the font,
+    /// as color emoji fonts do. The image is fitted to the (already supersampled)
+    /// `scale`, centered, and composited over white before computing luminance.
+    /// Returns `None` when the font has no usable image for the glyph.
+    fn from_raster_glyph(
+        font: &FontArc,
+        character: char,
+        scale: PxScale,
+        factor: u32,
+        cell: u32,
+    ) -> Option<Self> {
+        let raster = font.glyph_raster_image2(font.glyph_id(character), scale.y as u16)?;
+        if !matches!(raster.format, GlyphImageFormat::Png) {
+            return None;
+        }
+        let glyph = image::load_from_memory(raster.data).ok()?;
+
+        let size = cell * factor;
+        let target = (scale.y as u32).clamp(1, size);
+        let glyph = glyph.resize(target, target, imageops::FilterType::Triangle);
+        let mut canvas = RgbaImage::from_pixel(size, size, Rgba([255, 255, 255, 255]));
+        let x = (size - glyph.width()) / 2;
+        let y = (size - glyph.height()) / 2;
+        imageops::overlay(&mut canvas, &glyph.to_rgba8(), x as i64, y as i64);
+
+        let mut element = Element {
+            characteristics: canvas
+                .pixels()
+                .map(|p| Color::luminance_from_rgba(&p.0))
+                .collect(),
+            luminance: 0.0,
             character: Some(character),
             image: None,
-        })
+            log_weight: 0.0,
+        };
+        if factor > 1 {
+            element.characteristics = element.downsample(cell as usize);
+        }
+        element.luminance =
+            element.characteristics.iter().sum::<f64>() / element.characteristics.len() as f64;
+        log::debug!(
+            "Character: '{character}' rendered from a raster image, Luminance: {}",
+            element.luminance
+        );
+
+        Some(element)
     }
 
     /// Creates an element from an image tile by calculating its luminance characteristics.
-    pub fn from_image(image: DynamicImage) -> Result<Self> {
+    /// The tile's overall luminance is derived from its pixels using `sample`.
+    pub fn from_image(image: DynamicImage, sample: Sample) -> Result<Self> {
+        Self::from_image_weighted(image, sample, Color::REC601_WEIGHTS, false)
+    }
+
+    /// Creates an element from an image tile like [`Element::from_image`], weighting
+    /// the red, green, and blue channels by `weights` when computing luminance.
+    /// With `alpha_as_lightness`, each pixel's luminance is blended toward 1.0 (blank)
+    /// by its transparency, so transparent regions map to sparse glyphs.
+    /// Grayscale tiles use their gray values as luminance as they are, ignoring `weights`.
+    pub fn from_image_weighted(
+        image: DynamicImage,
+        sample: Sample,
+        weights: [f64; 3],
+        alpha_as_lightness: bool,
+    ) -> Result<Self> {
+        Self::from_image_with_luma(image, sample, weights, Luma::Yuv, alpha_as_lightness)
+    }
+
+    /// Like [`Element::from_image_weighted`], but measures each pixel with `luma`
+    /// (see [`Luma::of`]).
+    pub fn from_image_with_luma(
+        image: DynamicImage,
+        sample: Sample,
+        weights: [f64; 3],
+        luma: Luma,
+        alpha_as_lightness: bool,
+    ) -> Result<Self> {
         let (width, height) = image.dimensions();
         log::trace!("Image dimensions: {width}x{height}");
         if width == 0 || height == 0 {
-            return Err(anyhow!("Image has zero width or height."));
+            return Err(TypistError::EmptyImage);
         }
 
-        let mut characteristics: Vec<f64> = vec![];
-        let mut total_luminance: f64 = 0.0;
-
-        for (_, _, rgba) in image.pixels() {
-            let l = Color::luminance_from_rgba(&rgba.0);
-            total_luminance += l;
-            characteristics.push(l);
-        }
+        let lighten = |luminance: f64, alpha: f64| {
+            if alpha_as_lightness {
+                luminance * alpha + (1.0 - alpha)
+            } else {
+                luminance
+            }
+        };
+        // NOTE: grayscale pixels already are luminance, so they skip the channel weights.
+        let characteristics: Vec<f64> = match &image {
+            DynamicImage::ImageLuma8(gray) => gray
+                .pixels()
+                .map(|p| luma.of_gray(p[0] as f64 / 255.0))
+                .collect(),
+            DynamicImage::ImageLumaA8(gray) => gray
+                .pixels()
+                .map(|p| lighten(luma.of_gray(p[0] as f64 / 255.0), p[1] as f64 / 255.0))
+                .collect(),
+            DynamicImage::ImageLuma16(gray) => gray
+                .pixels()
+                .map(|p| luma.of_gray(p[0] as f64 / 65535.0))
+                .collect(),
+            DynamicImage::ImageLumaA16(gray) => gray
+                .pixels()
+                .map(|p| lighten(luma.of_gray(p[0] as f64 / 65535.0), p[1] as f64 / 65535.0))
+                .collect(),
+            _ => image
+                .pixels()
+                .map(|(_, _, rgba)| {
+                    let luminance = luma.of(&rgba.0, weights);
+                    lighten(luminance, rgba[3] as f64 / 255.0)
+                })
+                .collect(),
+        };
 
-        let luminance = total_luminance / (width * height) as f64;
+        let luminance = sample.luminance(&characteristics);
 
         Ok(Element {
             characteristics,
             luminance,
             character: None,
             image: Some(image),
+            log_weight: 0.0,
         })
     }
 
@@ -149,11 +538,7 @@ impl Element {
     /// to fall within the given luminance range.
     pub fn normalized(&mut self, min: f64, max: f64) -> Result<()> {
         if min >= max {
-            return Err(anyhow!(
-                "Invalid range: min ({}) must be less than max ({})",
-                min,
-                max
-            ));
+            return Err(TypistError::InvalidRange { min, max });
         }
 
         log::trace!(
@@ -176,12 +561,50 @@ impl Element {
         Ok(())
     }
 
+    /// Inverts the element's normalized characteristics and luminance,
+    /// turning dark into light and vice versa.
+    pub fn invert(&mut self) {
+        for value in &mut self.characteristics {
+            *value = 1.0 - *value;
+        }
+        self.luminance = 1.0 - self.luminance;
+    }
+
+    /// Pivots the element's normalized characteristics and luminance around
+    /// `pivot`, remapping each value to `|value - pivot| * scale`, where the scale
+    /// stretches the result back to [0, 1]. Tones far from the pivot on either side
+    /// become light and tones near it dark, like a solarized print.
+    ///
+    /// A pivot of 0 leaves the values as they are and a pivot of 1 inverts them.
+    pub fn pivot(&mut self, pivot: f64) {
+        let pivot = pivot.clamp(0.0, 1.0);
+        let scale = 1.0 / pivot.max(1.0 - pivot);
+        let remap = |value: f64| ((value - pivot).abs() * scale).clamp(0.0, 1.0);
+
+        for value in &mut self.characteristics {
+            *value = remap(*value);
+        }
+        self.luminance = remap(self.luminance);
+    }
+
+    /// Raises the element's raw characteristics to the power of `gamma` and
+    /// reduces them to a new luminance with `sample`. Black and white stay put,
+    /// while the tones in between darken for a gamma above 1 and brighten below 1,
+    /// so unlike a flat shift, the curve changes the shape that correlation compares.
+    pub fn gamma(&mut self, gamma: f64, sample: Sample) {
+        for value in &mut self.characteristics {
+            *value = value.clamp(0.0, 1.0).powf(gamma);
+        }
+        self.luminance = sample.luminance(&self.characteristics);
+    }
+
     /// Normalizes a single luminance value into the given range.
+    /// Values outside [min, max] are clamped to [0.0, 1.0].
     fn normalize(value: f64, min: f64, max: f64) -> f64 {
         if max - min < F64_ALMOST_ZERO {
             0.0
         } else {
-            (value - min) / (max - min)
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
         }
     }
 }
@@ -189,20 +612,264 @@ impl Element {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::FONT_DATA;
+    use crate::TEST_FONT_DATA;
+
+    fn at_scale(scale: f32) -> GlyphOptions {
+        GlyphOptions {
+            scale: PxScale::from(scale),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn element_from_char() {
         // let font_data = fs::read().unwrap();
-        let font = FontArc::try_from_slice(FONT_DATA).unwrap();
-        let scale = PxScale::from(16.0);
-        let element = Element::from_char(&font, 'A', scale);
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let element = Element::from_char(&font, 'A', &at_scale(16.0));
         assert!(element.is_ok());
         let element = element.unwrap();
         assert_eq!(element.character, Some('A'));
         assert!(!element.characteristics.is_empty());
     }
 
+    #[test]
+    fn bbox_alignment_centers_the_ink() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        for character in ['A', 'M', '.', 'W'] {
+            let element = Element::from_char(&font, character, &at_scale(16.0)).unwrap();
+            let rows: Vec<Vec<f64>> = element.characteristics_2d().map(|r| r.to_vec()).collect();
+            let inked = |values: Vec<bool>| {
+                let first = values.iter().position(|&v| v).unwrap();
+                let last = values.iter().rposition(|&v| v).unwrap();
+                (first, values.len() - 1 - last)
+            };
+            let (top, bottom) = inked(rows.iter().map(|r| r.iter().any(|&v| v < 1.0)).collect());
+            let (left, right) = inked(
+                (0..rows[0].len())
+                    .map(|x| rows.iter().any(|r| r[x] < 1.0))
+                    .collect(),
+            );
+            // the glyph's own bounding box sits in the middle of the cell, even for
+            // the period, whose outline starts far from the glyph origin.
+            assert!(top.abs_diff(bottom) <= 1, "{character}: {top} vs {bottom}");
+            assert!(left.abs_diff(right) <= 1, "{character}: {left} vs {right}");
+        }
+    }
+
+    #[test]
+    fn larger_glyph_scale_increases_ink() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let small = Element::from_char(&font, 'A', &at_scale(10.0)).unwrap();
+        let large = Element::from_char(&font, 'A', &at_scale(18.0)).unwrap();
+        // more ink means a darker cell, i.e. lower luminance.
+        assert!(large.luminance() < small.luminance());
+    }
+
+    #[test]
+    fn default_glyph_scale_fills_the_cell() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let default = crate::config::Config::default().glyph_scale;
+        assert_eq!(default, crate::IMAGE_FONT_SIZE as f32);
+
+        // the former 16px scale left a wider blank border than the 1px margin.
+        let ink = |scale: f32| {
+            1.0 - Element::from_char(&font, 'M', &at_scale(scale))
+                .unwrap()
+                .luminance()
+        };
+        assert!(ink(default) > ink(16.0));
+        let element = Element::from_char(&font, 'M', &at_scale(default)).unwrap();
+        let rows: Vec<&[f64]> = element.characteristics_2d().collect();
+        assert!(
+            rows[0]
+                .iter()
+                .chain(rows[rows.len() - 1])
+                .all(|&v| v == 1.0)
+        );
+    }
+
+    #[test]
+    fn larger_cells_reduce_average_ink() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let ink = |cell| {
+            let options = GlyphOptions {
+                cell,
+                ..Default::default()
+            };
+            let e = Element::from_char(&font, 'M', &options).unwrap();
+            assert_eq!(e.characteristics().len(), (cell * cell) as usize);
+            1.0 - e.luminance()
+        };
+        // the same glyph in a cell with a 1px, 3px, and 6px margin.
+        assert!(ink(20) > ink(24));
+        assert!(ink(24) > ink(30));
+    }
+
+    #[test]
+    fn subpixel_coverage_keeps_ink_and_spreads_edges() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        // baseline alignment centers the fractional advance, off the pixel grid.
+        let render = |subpixel| {
+            let options = GlyphOptions {
+                scale: PxScale::from(13.3),
+                align: Align::Baseline,
+                subpixel,
+                ..Default::default()
+            };
+            Element::from_char(&font, 'W', &options).unwrap()
+        };
+        let (pixel, area) = (render(false), render(true));
+        let ink = |e: &Element| 1.0 - e.luminance();
+        assert!((ink(&area) - ink(&pixel)).abs() < 0.01 * ink(&pixel));
+
+        // the weighted samples spread over more, partially inked pixels.
+        let partial = |e: &Element| {
+            e.characteristics()
+                .iter()
+                .filter(|&&v| v > 0.0 && v < 1.0)
+                .count()
+        };
+        assert!(partial(&area) > partial(&pixel));
+
+        // thin strokes keep fractional coverage: spread over the pixels they
+        // overlap, none of them saturates, while snapping fully inks some.
+        let inked = |e: &Element| e.characteristics().iter().filter(|&&v| v < 1.0).count();
+        assert_eq!(partial(&area), inked(&area));
+        assert!(partial(&pixel) < inked(&pixel));
+    }
+
+    #[test]
+    fn supersampling_smooths_characteristics() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let plain = Element::from_char(&font, 'W', &GlyphOptions::default()).unwrap();
+        let options = GlyphOptions {
+            supersample: 4,
+            ..Default::default()
+        };
+        let smooth = Element::from_char(&font, 'W', &options).unwrap();
+        assert_eq!(
+            smooth.characteristics().len(),
+            plain.characteristics().len()
+        );
+        assert_ne!(smooth.characteristics(), plain.characteristics());
+
+        // supersampling softens stroke edges, lowering the total variation along rows.
+        let variation = |e: &Element| {
+            e.characteristics_2d()
+                .flat_map(|row| row.windows(2).map(|w| (w[0] - w[1]).abs()))
+                .sum::<f64>()
+        };
+        assert!(variation(&smooth) < variation(&plain));
+        assert!((smooth.luminance() - plain.luminance()).abs() < 0.05);
+    }
+
+    #[test]
+    fn baseline_align_keeps_period_low() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        // the row index weighted by ink, i.e. the vertical center of the glyph.
+        let ink_center = |align| {
+            let options = GlyphOptions {
+                align,
+                ..Default::default()
+            };
+            let e = Element::from_char(&font, '.', &options).unwrap();
+            let (mut weighted, mut total) = (0.0, 0.0);
+            for (y, row) in e.characteristics_2d().enumerate() {
+                let ink: f64 = row.iter().map(|v| 1.0 - v).sum();
+                weighted += y as f64 * ink;
+                total += ink;
+            }
+            weighted / total
+        };
+        let middle = (IMAGE_SIZE as f64 - 1.0) / 2.0;
+        assert!((ink_center(Align::Bbox) - middle).abs() < 1.0);
+        assert!(ink_center(Align::Baseline) > middle + 2.0);
+    }
+
+    #[test]
+    fn glyph_ink_empty_glyph_is_zero() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let ink = glyph_ink(&font, ' ', PxScale::from(18.0)).unwrap();
+        assert_eq!(ink, 0.0);
+    }
+
+    #[test]
+    fn glyph_ink_grows_with_ink() {
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let scale = PxScale::from(18.0);
+        let dot = glyph_ink(&font, '.', scale).unwrap();
+        let m = glyph_ink(&font, 'M', scale).unwrap();
+        assert!(dot > 0.0 && dot < m && m < 1.0);
+    }
+
+    #[test]
+    fn characteristics_2d_round_trips_rows() {
+        let characteristics: Vec<f64> = (0..9).map(|v| v as f64).collect();
+        let element = Element::new(characteristics.clone(), 0.5, None, None);
+        assert_eq!(element.width(), 3);
+        assert_eq!(element.height(), 3);
+
+        let rows: Vec<&[f64]> = element.characteristics_2d().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], &[3.0, 4.0, 5.0]);
+        assert_eq!(rows.concat(), characteristics);
+    }
+
+    #[test]
+    fn downsample_averages_blocks() {
+        #[rustfmt::skip]
+        let characteristics = vec![
+            0.0, 0.0, 1.0, 1.0,
+            0.0, 0.0, 1.0, 1.0,
+            0.5, 0.5, 0.25, 0.5,
+            0.5, 0.5, 0.5, 0.25,
+        ];
+        let element = Element::new(characteristics.clone(), 0.5, None, None);
+        assert_eq!(element.downsample(2), vec![0.0, 1.0, 0.5, 0.375]);
+        assert_eq!(element.downsample(4), characteristics);
+        assert_eq!(element.downsample(8), characteristics);
+    }
+
+    #[test]
+    fn characteristics_2d_empty_element() {
+        let element = Element::default();
+        assert_eq!(element.width(), 0);
+        assert_eq!(element.height(), 0);
+        assert_eq!(element.characteristics_2d().count(), 0);
+    }
+
+    #[test]
+    fn sample_average() {
+        assert_eq!(Sample::Average.luminance(&[0.0, 0.5, 1.0, 0.5]), 0.5);
+        assert_eq!(Sample::Average.luminance(&[]), 0.0);
+    }
+
+    #[test]
+    fn sample_median_ignores_outliers() {
+        assert_eq!(Sample::Median.luminance(&[0.2, 0.9, 0.0, 0.2, 0.3]), 0.2);
+        assert_eq!(Sample::Median.luminance(&[0.25, 0.75, 0.0, 1.0]), 0.5);
+    }
+
+    #[test]
+    fn sample_dominant_keeps_most_common_tone() {
+        let values = [0.9, 0.9, 0.9, 0.1, 0.0];
+        assert!((Sample::Dominant.luminance(&values) - 0.9).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_skips_image() {
+        let image = DynamicImage::new_rgba8(2, 2);
+        let element = Element::new(vec![0.25, 0.5, 0.75, 1.0], 0.625, Some('A'), Some(image));
+
+        let json = serde_json::to_string(&element).unwrap();
+        let restored: Element = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.characteristics, element.characteristics);
+        assert_eq!(restored.luminance, element.luminance);
+        assert_eq!(restored.character, element.character);
+        assert!(restored.image.is_none());
+    }
+
     #[test]
     fn normalized_invalid_range_returns_err() {
         let mut element = Element::new(vec![0.5, 0.6, 0.7], 0.6, Some('A'), None);
@@ -218,4 +885,158 @@ mod tests {
         assert_eq!(element.characteristics, vec![0.0, 0.5, 1.0]);
         assert_eq!(element.luminance, 0.5);
     }
+
+    #[test]
+    fn normalize_clamps_out_of_range_values() {
+        assert_eq!(Element::normalize(0.0, 0.25, 0.75), 0.0);
+        assert_eq!(Element::normalize(1.0, 0.25, 0.75), 1.0);
+        assert_eq!(Element::normalize(0.5, 0.25, 0.75), 0.5);
+    }
+
+    #[test]
+    fn normalized_clamps_characteristics_outside_range() {
+        let mut element = Element::new(vec![-0.5, 0.5, 1.5], 0.5, None, None);
+        element.normalized(0.0, 1.0).unwrap();
+        assert_eq!(element.characteristics, vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn invert_flips_characteristics_and_luminance() {
+        let mut element = Element::new(vec![0.0, 0.25, 1.0], 0.25, None, None);
+        element.invert();
+        assert_eq!(element.characteristics, vec![1.0, 0.75, 0.0]);
+        assert_eq!(element.luminance, 0.75);
+    }
+
+    #[test]
+    fn pivot_at_the_ends_is_monotonic() {
+        let values = vec![0.0, 0.25, 0.5, 1.0];
+
+        let mut element = Element::new(values.clone(), 0.25, None, None);
+        element.pivot(0.0);
+        assert_eq!(element.characteristics, values);
+        assert_eq!(element.luminance, 0.25);
+
+        let mut element = Element::new(values.clone(), 0.25, None, None);
+        element.pivot(1.0);
+        let mut inverted = Element::new(values, 0.25, None, None);
+        inverted.invert();
+        assert_eq!(element.characteristics, inverted.characteristics);
+        assert_eq!(element.luminance, inverted.luminance);
+    }
+
+    #[test]
+    fn pivot_folds_tones_around_the_pivot() {
+        let mut element = Element::new(vec![0.0, 0.25, 0.5, 0.75, 1.0], 0.5, None, None);
+        element.pivot(0.5);
+        assert_eq!(element.characteristics, vec![1.0, 0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(element.luminance, 0.0);
+
+        // the longer side of the pivot spans the full range.
+        let mut element = Element::new(vec![0.0, 0.25, 1.0], 0.0, None, None);
+        element.pivot(0.25);
+        assert_eq!(element.characteristics, vec![1.0 / 3.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn gamma_bends_the_midtones_and_keeps_the_ends() {
+        let mut element = Element::new(vec![0.0, 0.25, 1.0], 0.0, None, None);
+        element.gamma(0.5, Sample::Average);
+        assert_eq!(element.characteristics, vec![0.0, 0.5, 1.0]);
+        assert_eq!(element.luminance, 0.5);
+        element.gamma(2.0, Sample::Median);
+        assert_eq!(element.characteristics, vec![0.0, 0.25, 1.0]);
+        assert_eq!(element.luminance, 0.25);
+    }
+
+    #[test]
+    fn is_placeholder_only_for_the_default_element() {
+        assert!(Element::default().is_placeholder());
+        assert!(Element::new(vec![], 0.5, None, None).is_placeholder());
+        assert!(!Element::new(vec![], 0.5, Some('A'), None).is_placeholder());
+        assert!(!Element::new(vec![0.5; 4], 0.5, None, None).is_placeholder());
+
+        let font = FontArc::try_from_slice(TEST_FONT_DATA).unwrap();
+        let glyph = Element::from_char(&font, 'A', &at_scale(IMAGE_SIZE as f32)).unwrap();
+        assert!(!glyph.is_placeholder());
+    }
+
+    #[test]
+    fn weight_defaults_to_one() {
+        let element = Element::new(vec![], 0.5, Some('A'), None);
+        assert_eq!(element.weight(), 1.0);
+        assert_eq!(Element::default().weight(), 1.0);
+        assert!((element.clone().with_weight(0.5).weight() - 0.5).abs() < 1e-12);
+        assert_eq!(element.clone().with_weight(0.0).weight(), 1.0);
+        assert_eq!(element.with_weight(f64::NAN).weight(), 1.0);
+    }
+
+    #[test]
+    fn luminance_u8_maps_to_gray_levels() {
+        let gray = |l| Element::new(vec![], l, None, None).luminance_u8();
+        assert_eq!(gray(0.0), 0);
+        assert_eq!(gray(0.5), 128);
+        assert_eq!(gray(1.0), 255);
+        assert_eq!(gray(-0.5), 0);
+        assert_eq!(gray(1.5), 255);
+    }
+
+    #[test]
+    fn from_image_grayscale_maps_values_directly() {
+        let gray = image::GrayImage::from_fn(2, 1, |x, _| image::Luma([x as u8 * 255]));
+        let element = Element::from_image_weighted(
+            DynamicImage::ImageLuma8(gray),
+            Sample::Average,
+            [1.0, 0.0, 0.0],
+            false,
+        )
+        .unwrap();
+        assert_eq!(element.characteristics(), [0.0, 1.0]);
+        assert_eq!(element.luminance(), 0.5);
+
+        let gray = image::ImageBuffer::from_pixel(1, 1, image::Luma([16384u16]));
+        let element =
+            Element::from_image(DynamicImage::ImageLuma16(gray), Sample::Average).unwrap();
+        assert_eq!(element.characteristics(), [16384.0 / 65535.0]);
+    }
+
+    #[test]
+    fn from_image_grayscale_alpha_respects_alpha_as_lightness() {
+        let gray = image::GrayAlphaImage::from_pixel(1, 1, image::LumaA([51, 0]));
+        let image = DynamicImage::ImageLumaA8(gray);
+        let kept = Element::from_image_weighted(
+            image.clone(),
+            Sample::Average,
+            Color::REC601_WEIGHTS,
+            false,
+        )
+        .unwrap();
+        assert_eq!(kept.characteristics(), [0.2]);
+        let lightened =
+            Element::from_image_weighted(image, Sample::Average, Color::REC601_WEIGHTS, true)
+                .unwrap();
+        assert_eq!(lightened.characteristics(), [1.0]);
+    }
+
+    #[test]
+    fn alpha_as_lightness_lightens_transparent_tiles() {
+        let tile =
+            |alpha| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, alpha])));
+        let luminance = |alpha, enabled| {
+            Element::from_image_weighted(
+                tile(alpha),
+                Sample::Average,
+                Color::REC601_WEIGHTS,
+                enabled,
+            )
+            .unwrap()
+            .luminance()
+        };
+        // opaque tiles are unaffected.
+        assert_eq!(luminance(255, true), 0.0);
+        assert_eq!(luminance(255, false), 0.0);
+        // transparent tiles become blank only in this mode.
+        assert_eq!(luminance(0, true), 1.0);
+        assert_eq!(luminance(0, false), 0.0);
+    }
 }
@@ -0,0 +1,95 @@
+use thiserror::Error;
+
+/// The error type returned by the typistapp library.
+#[derive(Debug, Error)]
+pub enum TypistError {
+    /// The font data could not be parsed.
+    #[error("Failed to load font: {0}")]
+    FontParse(#[from] ab_glyph::InvalidFont),
+
+    /// A font file could not be read.
+    #[error("Failed to read font file {}: {source}", .path.display())]
+    FontFile {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    /// An image could not be decoded or processed.
+    #[error("Failed to decode image: {0}")]
+    ImageDecode(#[from] image::ImageError),
+
+    /// The typeset has no characters to render the art with.
+    #[error("Typeset contains no characters")]
+    EmptyTypeset,
+
+    /// The requested output width is zero.
+    #[error("Invalid length: {0}, the art must be at least 1 character wide")]
+    InvalidLength(u32),
+
+    /// A normalization range is empty or inverted.
+    #[error("Invalid range: min ({min}) must be less than max ({max})")]
+    InvalidRange { min: f64, max: f64 },
+
+    /// A character has no outline in the font.
+    #[error("Failed to outline glyph for character: {0}")]
+    GlyphOutline(char),
+
+    /// An image tile has zero width or height.
+    #[error("Image has zero width or height.")]
+    EmptyImage,
+
+    /// A crop region is empty or does not fit within the image.
+    #[error(
+        "Invalid region {width}x{height} at ({x}, {y}): it must be non-empty and fit within the {image_width}x{image_height} image"
+    )]
+    InvalidRegion {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        image_width: u32,
+        image_height: u32,
+    },
+
+    /// A line of a typeset file could not be parsed.
+    #[error("Invalid typeset line {line}: {message}")]
+    InvalidTypeset { line: usize, message: String },
+
+    /// A color string is not a valid hex color.
+    #[error("Invalid color {0:?}: expected a hex color like #rgb, #rrggbb, or #rrggbbaa")]
+    InvalidColor(String),
+
+    /// Input data does not match the expected dimensions.
+    #[error("Invalid input size: got {actual} values, expected {expected}")]
+    InputSize { actual: usize, expected: usize },
+
+    /// Some tiles found no matching glyph in strict mode.
+    #[error("{} tile(s) have no match, at (column, row): {}", .positions.len(), format_positions(.positions))]
+    UnmatchedTiles { positions: Vec<(u32, u32)> },
+
+    /// An I/O operation failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A `.npy` file is malformed or holds an unsupported array.
+    #[cfg(feature = "npy")]
+    #[error("Invalid .npy file: {0}")]
+    Npy(String),
+
+    /// The `ffmpeg` tool failed to extract video frames.
+    #[cfg(feature = "video")]
+    #[error("ffmpeg failed: {0}")]
+    Ffmpeg(String),
+}
+
+/// Formats tile coordinates as a comma-separated list of `(column, row)` pairs.
+fn format_positions(positions: &[(u32, u32)]) -> String {
+    positions
+        .iter()
+        .map(|(x, y)| format!("({x}, {y})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A specialized `Result` type for typistapp operations.
+pub type Result<T> = std::result::Result<T, TypistError>;
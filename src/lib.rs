@@ -1,22 +1,152 @@
-use ab_glyph::PxScale;
-use std::sync::LazyLock;
-
 pub mod color;
+pub mod config;
+pub mod conversion;
+pub mod converter;
 pub mod correlation;
 pub mod element;
+pub mod error;
+pub mod metric;
 pub mod model;
+#[cfg(feature = "npy")]
+pub mod npy;
+mod typesetter;
+#[cfg(feature = "video")]
+pub mod video;
 pub mod view;
 
 const F64_ALMOST_ZERO: f64 = 1e-12;
 const NUM_OF_CANDIDATES: usize = 16;
+const NUM_OF_DOMINANT_BINS: usize = 32;
 const IMAGE_FONT_SIZE: u32 = 18;
 const IMAGE_MARGIN: u32 = 1;
 const IMAGE_SIZE: u32 = IMAGE_FONT_SIZE + IMAGE_MARGIN * 2;
 const FULL_WIDTH_SPACE: char = '　';
+const SHADE_RAMP: [char; 5] = ['█', '▓', '▒', '░', ' '];
+/// The classic ASCII ramp, from blank paper to the most ink.
+pub const ASCII_RAMP: &str = " .:-=+*#%@";
+const BRAILLE_BLANK: u32 = 0x2800;
+// bit of each dot in a Braille cell, indexed by [row][column].
+const BRAILLE_DOT_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+// how far a glyph's log usage weight shifts its match score.
+const GLYPH_WEIGHT_BIAS: f64 = 0.1;
 const PER_CHARACTER_DELAY_MS: u64 = 10;
+const PER_ROW_DELAY_MS: u64 = 50;
+/// The tab stop interval, in columns, of text animated with [`view::View::text_lines`].
+pub const TAB_WIDTH: u16 = 8;
 
-static GLYPH_SCALE: LazyLock<PxScale> = LazyLock::new(|| PxScale::from(16.0));
+/// The recommended range of output widths, in characters.
+pub const MIN_LENGTH: u32 = 32;
+pub const MAX_LENGTH: u32 = 128;
 
 pub const TYPESET: &str = include_str!("../assets/typeset.txt");
 pub const FONT_DATA: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/", "NotoSansJP-Regular.otf"));
+/// A tiny committed font with glyphs for `.`, `A`, `M`, and `W` only, so that
+/// tests render the same glyphs whatever font the build downloaded.
+#[cfg(any(test, feature = "test-font"))]
+pub const TEST_FONT_DATA: &[u8] = include_bytes!("../resources/test-font.ttf");
+
+/// Returns the characters of the embedded [`TYPESET`] in order, without line
+/// breaks or duplicates. This is the palette the command-line tool uses.
+pub fn default_typeset() -> Vec<char> {
+    let mut seen = std::collections::HashSet::new();
+    TYPESET
+        .chars()
+        .filter(|&c| c != '\n' && c != '\r' && seen.insert(c))
+        .collect()
+}
+
+/// Parses a typeset file into its characters, in order and without duplicates,
+/// and the usage weights of the annotated ones (see [`Config::glyph_weights`]).
+///
+/// A line of the form `char<TAB>weight` lists a single character with a
+/// positive weight; any other line lists each of its characters with the
+/// default weight of 1, as in the embedded [`TYPESET`]. Empty lines are skipped.
+///
+/// [`Config::glyph_weights`]: crate::config::Config::glyph_weights
+pub fn parse_typeset(
+    text: &str,
+) -> error::Result<(Vec<char>, std::collections::HashMap<char, f64>)> {
+    let mut seen = std::collections::HashSet::new();
+    let mut characters = Vec::new();
+    let mut weights = std::collections::HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let invalid = |message: &str| error::TypistError::InvalidTypeset {
+            line: i + 1,
+            message: message.to_string(),
+        };
+
+        let Some((glyph, weight)) = line.split_once('\t') else {
+            characters.extend(line.chars().filter(|&c| seen.insert(c)));
+            continue;
+        };
+        let mut glyph = glyph.chars();
+        let (Some(c), None) = (glyph.next(), glyph.next()) else {
+            return Err(invalid("expected a single character before the tab"));
+        };
+        let weight: f64 = weight
+            .trim()
+            .parse()
+            .map_err(|_| invalid("the weight is not a number"))?;
+        if !(weight.is_finite() && weight > 0.0) {
+            return Err(invalid("the weight must be a positive number"));
+        }
+        if seen.insert(c) {
+            characters.push(c);
+            weights.insert(c, weight);
+        }
+    }
+
+    Ok((characters, weights))
+}
+
+/// Advances the SplitMix64 state and returns the next pseudo-random value.
+pub(crate) fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_typeset_has_no_line_breaks_or_duplicates() {
+        let typeset = default_typeset();
+        assert!(!typeset.is_empty());
+        assert!(!typeset.contains(&'\n'));
+        let unique: std::collections::HashSet<char> = typeset.iter().copied().collect();
+        assert_eq!(unique.len(), typeset.len());
+    }
+
+    #[test]
+    fn parse_typeset_reads_weights() {
+        let (characters, weights) = parse_typeset("AM\n\n.\t0.5\nW\t2\nA\t3\n").unwrap();
+        assert_eq!(characters, ['A', 'M', '.', 'W']);
+        assert_eq!(weights.len(), 2);
+        assert_eq!((weights[&'.'], weights[&'W']), (0.5, 2.0));
+
+        let (characters, weights) = parse_typeset(TYPESET).unwrap();
+        assert_eq!(characters, default_typeset());
+        assert!(weights.is_empty());
+    }
+
+    #[test]
+    fn parse_typeset_rejects_invalid_lines() {
+        for (text, line) in [
+            ("A\nAB\t1", 2),
+            ("A\tx", 1),
+            ("\t1", 1),
+            ("A\t0", 1),
+            ("A\t-1", 1),
+        ] {
+            assert!(matches!(
+                parse_typeset(text),
+                Err(error::TypistError::InvalidTypeset { line: l, .. }) if l == line
+            ));
+        }
+    }
+}
@@ -1,17 +1,373 @@
-use anyhow::Result;
-use clap::Parser;
-use typistapp::{model::Model, view::View};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use typistapp::{FONT_DATA, TYPESET};
+use ab_glyph::PxScale;
+use anyhow::{Context, Result, bail};
+use clap::{Parser, ValueEnum, builder::TypedValueParser};
+use crossterm::{cursor, execute, style::Color, terminal};
+use image::{DynamicImage, GrayImage, Rgba};
+use typistapp::{
+    color::{ColorSpace, Luma},
+    config::Config,
+    conversion::ConversionResult,
+    element::{Align, Sample},
+    metric::Metric,
+    model::{Model, SortOrder, TileFilter},
+    view::{LineEnding, Reveal, RevealOrder, TextAttribute, View},
+};
+
+use typistapp::{ASCII_RAMP, FONT_DATA, MAX_LENGTH, MIN_LENGTH, default_typeset, parse_typeset};
+
+/// The size of the synthetic image converted by `--selftest`, in pixels.
+const SELFTEST_IMAGE_SIZE: (u32, u32) = (640, 160);
+
+/// The number of tonal gaps listed by `--palette-report`.
+const PALETTE_REPORT_GAPS: usize = 5;
+
+/// How long one `--breathe` cycle lasts, in seconds.
+const BREATHE_PERIOD_SECS: f64 = 2.0;
+
+/// What the converted art is written as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Animated text in the terminal.
+    #[default]
+    Text,
+    /// A PNG of the glyphs drawn in their tile colors over the source image.
+    Overlay,
+    /// A binary PGM with the matched glyph luminance of each tile as one pixel.
+    Pgm,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        f.write_str(value.get_name())
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 struct Args {
-    #[arg(value_parser = clap::value_parser!(u32).range(32..=128))]
-    length: u32,
+    #[arg(
+        value_parser = clap::value_parser!(u32).range(MIN_LENGTH as i64..=MAX_LENGTH as i64),
+        required_unless_present_any = ["play", "fit_terminal", "char_budget", "auto_length", "selftest", "text"]
+    )]
+    length: Option<u32>,
+
+    /// Derive the output width from the terminal width instead of LENGTH.
+    #[arg(long, conflicts_with = "length")]
+    fit_terminal: bool,
+
+    /// Derive the output width as the largest whose total character count fits N.
+    #[arg(long, value_name = "N", conflicts_with_all = ["length", "fit_terminal"])]
+    char_budget: Option<u64>,
+
+    /// Convert at each candidate width and keep the one with the best mean score (default: 48,64,96).
+    #[arg(
+        long,
+        value_name = "LENGTHS",
+        num_args = 0..=1,
+        value_delimiter = ',',
+        default_missing_value = "48,64,96",
+        value_parser = clap::value_parser!(u32).range(MIN_LENGTH as i64..=MAX_LENGTH as i64),
+        conflicts_with_all = ["length", "fit_terminal", "char_budget"]
+    )]
+    auto_length: Option<Vec<u32>>,
+
+    /// The image to convert. With the `network` feature, http(s) URLs are fetched as well.
+    #[arg(short, long, required_unless_present_any = ["play", "selftest", "text"])]
+    image: Option<String>,
+
+    /// Skip conversion and animate the text of this file (`-` for stdin) with the typing effect.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["image", "play", "selftest"])]
+    text: Option<String>,
+
+    /// How the art is revealed: one character, one row, or all at once.
+    #[arg(long, value_enum, default_value_t = Reveal::Char)]
+    reveal: Reveal,
+
+    /// Pause this many milliseconds after each revealed character or row (default: 10 or 50).
+    #[arg(long, value_name = "MS")]
+    delay_ms: Option<u64>,
+
+    /// Print the art as plain rows of text with no escape codes, cursor movement, or animation.
+    #[arg(long, conflicts_with_all = ["fg", "bg_color", "attr", "compare"])]
+    plain: bool,
+
+    /// The newline style of plain text output and of `--frames` files.
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    line_ending: LineEnding,
+
+    /// The order in which characters are placed by the per-character reveal.
+    #[arg(long, value_enum, default_value_t = RevealOrder::LrTb)]
+    reveal_order: RevealOrder,
+
+    /// Reveal the n-th character (or row) at the n-th millisecond timestamp listed in this file.
+    #[arg(long, value_name = "PATH", conflicts_with = "plain")]
+    beat_file: Option<std::path::PathBuf>,
+
+    /// The seed of the random reveal order (defaults to the current time).
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// How each tile's luminance is sampled from its pixels.
+    #[arg(long, value_enum, default_value_t = Sample::Average)]
+    sample: Sample,
+
+    /// Print the typeset's largest tonal gaps to stderr and exit without converting.
+    #[arg(long)]
+    palette_report: bool,
+
+    /// The pixel scale at which glyphs are rendered (defaults to the font size).
+    #[arg(long)]
+    glyph_scale: Option<f32>,
+
+    /// The blank border in pixels on each side of a glyph cell (default: 1).
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(0..=32))]
+    glyph_margin: Option<u32>,
+
+    /// Read the typeset from this file, one `char<TAB>weight` or a run of characters per line.
+    #[arg(long, value_name = "FILE")]
+    typeset: Option<std::path::PathBuf>,
+
+    /// Never use these characters, whichever typeset they come from.
+    #[arg(long, value_name = "CHARS")]
+    glyph_denylist: Option<String>,
+
+    /// How the typeset is ordered before matching; `ink` and `none` use a slower linear search.
+    #[arg(long, value_enum, default_value_t = SortOrder::Luminance)]
+    sort: SortOrder,
+
+    /// Sort the typeset with a faster unstable sort; glyphs of equal tone may swap places.
+    #[arg(long)]
+    no_sort_stability: bool,
+
+    /// Downsample characteristics to N×N before matching for speed (default: full resolution).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    match_resolution: Option<u32>,
+
+    /// Take every glyph within this luminance distance of a tile as a candidate (default: the 16 nearest).
+    #[arg(long, value_name = "DELTA", value_parser = parse_luminance_band)]
+    luminance_band: Option<f64>,
+
+    /// Score only the K tonally closest glyphs with --metric (default: 16, or the whole band).
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u32).range(1..))]
+    metric_topk: Option<u32>,
+
+    /// Normalize each tile against the tones of the WINDOW×WINDOW tiles around it, not the whole image.
+    #[arg(long, value_name = "WINDOW", value_parser = clap::value_parser!(u32).range(2..))]
+    local_contrast: Option<u32>,
+
+    /// Reuse the glyph matched to a flat tile for other flat tiles of the same tone.
+    #[arg(long)]
+    match_cache: bool,
+
+    /// Keep the partial bottom tile (padded with white) instead of cropping it.
+    #[arg(long)]
+    retain_ratio: bool,
+
+    /// Pad the image above and below with `--background` so it fills whole tile rows, losing no pixels.
+    #[arg(long)]
+    letterbox: bool,
+
+    /// Crop the source to the pixel rectangle X,Y,W,H before anything else.
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_region)]
+    region: Option<[u32; 4]>,
+
+    /// A grayscale image, shaped like the source, whose bright areas are matched more carefully.
+    #[arg(long, value_name = "PATH", conflicts_with = "letterbox")]
+    mask: Option<std::path::PathBuf>,
+
+    /// The color of the `--letterbox` bars, given as `#rgb`, `#rrggbb`, or `#rrggbbaa` (default: white).
+    #[arg(long, value_name = "HEX", value_parser = parse_rgba, requires = "letterbox")]
+    background: Option<[u8; 4]>,
+
+    /// Enhance the edges of the source with this strength before conversion, for a line-drawing look.
+    #[arg(long, value_name = "STRENGTH", default_value_t = 0.0, value_parser = parse_outline)]
+    outline: f32,
+
+    /// Render glyphs at 1x, 2x, or 4x resolution before downsampling them into their cells.
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = clap::builder::PossibleValuesParser::new(["1", "2", "4"])
+            .map(|s| s.parse::<u32>().unwrap())
+    )]
+    supersample: u32,
+
+    /// Spread glyph coverage over the pixels it overlaps at subpixel positions, for smoother glyphs.
+    #[arg(long)]
+    subpixel_coverage: bool,
+
+    /// Invert the tile luminance used for matching, placing dense glyphs on bright areas.
+    #[arg(long)]
+    invert_luminance: bool,
+
+    /// Pivot the tile luminance used for matching around this tone (0-1), for a solarized look.
+    #[arg(long, value_name = "TONE", value_parser = parse_pivot)]
+    pivot: Option<f64>,
+
+    /// Fail instead of leaving blanks when any tile finds no matching glyph.
+    #[arg(long)]
+    strict: bool,
+
+    /// The color space whose luma coefficients weight the channels of the source.
+    #[arg(long, value_enum, default_value_t = ColorSpace::Bt601)]
+    colorspace: ColorSpace,
+
+    /// Weights of the red, green, and blue channels for the source luminance (default: Rec. 601).
+    #[arg(
+        long,
+        value_name = "R,G,B",
+        value_parser = parse_channel_weights,
+        conflicts_with = "colorspace"
+    )]
+    channel_weights: Option<[f64; 3]>,
+
+    /// How source pixels are measured: YUV luma, or the perceptually uniform CIELAB L*.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Luma::Yuv,
+        conflicts_with_all = ["colorspace", "channel_weights"]
+    )]
+    luma: Luma,
+
+    /// Lighten transparent pixels toward blank in proportion to their transparency.
+    #[arg(long)]
+    alpha_as_lightness: bool,
+
+    /// Render Braille dot patterns from the source luminance instead of matching glyphs.
+    #[arg(long)]
+    braille: bool,
+
+    /// Map luminance straight onto this ramp, from paper to ink, instead of matching glyphs.
+    #[arg(
+        long,
+        value_name = "CHARS",
+        num_args = 0..=1,
+        default_missing_value = ASCII_RAMP,
+        allow_hyphen_values = true,
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        conflicts_with = "braille"
+    )]
+    ramp: Option<String>,
+
+    /// Loop the art while its mid-tones gently oscillate by up to this amount (default: 0.15).
+    #[arg(
+        long,
+        value_name = "AMPLITUDE",
+        num_args = 0..=1,
+        default_missing_value = "0.15",
+        value_parser = parse_amplitude,
+        conflicts_with_all = ["braille", "ramp", "plain", "compare"]
+    )]
+    breathe: Option<f64>,
+
+    /// The character placed in cells without a match and in the margin (default: full-width space).
+    #[arg(long, value_name = "CHAR")]
+    fill_char: Option<char>,
+
+    /// Surround the art with this many blank rows and columns on all sides.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    margin: u32,
+
+    /// How candidate glyphs are scored against each tile.
+    #[arg(long, value_enum, default_value_t = Metric::Pearson)]
+    metric: Metric,
+
+    /// The filter used to resize the source to the tile grid.
+    #[arg(long, value_enum, default_value_t = TileFilter::Triangle)]
+    tile_filter: TileFilter,
+
+    /// Downscale large images to twice the target size before the final resize.
+    #[arg(long)]
+    two_stage_resize: bool,
 
-    #[arg(short, long)]
-    image: String,
+    /// Keep only one glyph of each set of case variants, such as `A` and `a`.
+    #[arg(long)]
+    collapse_case: bool,
+
+    /// How glyphs are positioned within their cells.
+    #[arg(long, value_enum, default_value_t = Align::Bbox)]
+    align: Align,
+
+    /// Print the dimensions, typeset size, and luminance ranges, then exit without converting.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Prepend this string to every row of text output, e.g. `// ` for a code comment.
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "",
+        allow_hyphen_values = true
+    )]
+    line_prefix: String,
+
+    /// Append this string to every row of text output.
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "",
+        allow_hyphen_values = true
+    )]
+    line_suffix: String,
+
+    /// Print the whole art in a single foreground color, given as `#rrggbb`.
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_color)]
+    fg: Option<Color>,
+
+    /// Paint every cell's background in its tile's average color, with black or white glyphs.
+    #[arg(long)]
+    bg_color: bool,
+
+    /// Print the typeset characters that the art never uses to stderr after the animation.
+    #[arg(long)]
+    report_unused_glyphs: bool,
+
+    /// Print every cell as `char:score` to stderr after the animation.
+    #[arg(long)]
+    debug_grid: bool,
+
+    /// Print the art in normal, bold, or dim text.
+    #[arg(long, value_enum, default_value_t = TextAttribute::Normal)]
+    attr: TextAttribute,
+
+    /// What to produce: animated text, or an image written to `--output`.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Space the glyphs of `--format overlay` by their advance widths instead of whole tiles.
+    #[arg(long)]
+    proportional: bool,
+
+    /// The file written by image output formats.
+    #[arg(short, long, value_name = "FILE", required_if_eq_any([("format", "overlay"), ("format", "pgm")]))]
+    output: Option<std::path::PathBuf>,
+
+    /// Show a shade-block thumbnail of the source above the art.
+    #[arg(long)]
+    compare: bool,
+
+    /// Treat the input as a video and write one numbered art file per frame into this directory.
+    #[cfg(feature = "video")]
+    #[arg(long, value_name = "DIR", conflicts_with = "mask")]
+    frames: Option<std::path::PathBuf>,
+
+    /// Play the numbered art files in this directory as a terminal animation.
+    #[arg(long, value_name = "DIR")]
+    play: Option<std::path::PathBuf>,
+
+    /// The frame rate used by `--play` and `--breathe`.
+    #[arg(long, default_value_t = 12.0)]
+    fps: f64,
+
+    /// Convert a built-in gradient image, check the result, and print PASS or FAIL.
+    #[arg(long)]
+    selftest: bool,
 }
 
 fn main() -> Result<()> {
@@ -22,27 +378,704 @@ fn main() -> Result<()> {
 }
 
 fn run(args: &Args) -> Result<()> {
-    let mut chars = vec![];
-    for c in TYPESET.chars() {
-        if c != '\n' {
-            chars.push(c);
-        }
+    if args.selftest {
+        return selftest();
+    }
+    if let Some(dir) = &args.play {
+        return play(args, dir);
     }
+    if let Some(path) = &args.text {
+        return type_text(args, path);
+    }
+    let path = args.image.as_deref().context("--image is required")?;
+
+    let (chars, weights) = typeset(args)?;
     log::debug!("Typeset: {chars:?}");
 
-    let image = image::open(&args.image)?;
-    log::debug!("Image loaded: {}", args.image);
+    #[cfg(feature = "npy")]
+    if path.ends_with(".npy") {
+        return convert_npy(args, &chars, &weights, path);
+    }
+
+    #[cfg(feature = "video")]
+    if let Some(dir) = &args.frames {
+        let length = output_length(args, None)?;
+        return write_frames(args, &chars, &weights, length, path, dir);
+    }
 
-    let mut m = Model::new(args.length, &image, &chars, FONT_DATA)?;
+    let image = load_image(path)?;
+    log::debug!("Image loaded: {path}");
+    let mut config = config(args, &weights);
+    if let Some(mask_path) = &args.mask {
+        config.importance_mask = Some(Arc::new(importance_mask(args, mask_path, &image)?));
+    }
+    let image = match args.region {
+        Some(region) => Model::crop_region(&image, region)?,
+        None => image,
+    };
+    let length = match &args.auto_length {
+        Some(lengths) => {
+            let (length, score) = Model::auto_length(&image, lengths, &chars, FONT_DATA, &config)?;
+            eprintln!("Auto length: {length} won with a mean score of {score:.4}");
+            length
+        }
+        None => output_length(args, Some(&image))?,
+    };
+    let image = if args.letterbox {
+        Model::letterbox(
+            &image,
+            length,
+            config.cell_size(),
+            Rgba(args.background.unwrap_or([255; 4])),
+        )
+    } else {
+        image
+    };
+
+    let fill_char = config.fill_char;
+    let mut m = Model::with_config(length, &image, &chars, FONT_DATA, config)?;
     log::debug!("Model created: {m:?}");
 
-    let s = m.convert()?;
-    for line in &s {
+    if args.palette_report {
+        let gaps = m.typeset_gaps(PALETTE_REPORT_GAPS)?;
+        eprintln!("Largest tonal gaps in the typeset (0.0 = darkest, 1.0 = lightest):");
+        for (lower, upper) in gaps {
+            eprintln!(
+                "  {lower:.3} .. {upper:.3} (width {:.3}): add glyphs with luminance around {:.3}",
+                upper - lower,
+                (lower + upper) / 2.0
+            );
+        }
+        return Ok(());
+    }
+
+    if args.dry_run {
+        println!("{}", m.plan()?);
+        return Ok(());
+    }
+
+    if args.format != OutputFormat::Text {
+        if args.braille || args.ramp.is_some() {
+            bail!(
+                "--format {} needs matched glyphs and cannot be used with --braille or --ramp",
+                args.format
+            );
+        }
+        let path = args.output.as_deref().context("--output is required")?;
+        let result = m.convert()?;
+        if args.format == OutputFormat::Overlay {
+            m.overlay(&result)?
+                .save(path)
+                .with_context(|| format!("Failed to write overlay: {}", path.display()))?;
+        } else {
+            std::fs::write(path, result.to_pgm())
+                .with_context(|| format!("Failed to write PGM: {}", path.display()))?;
+        }
+        log::info!("Wrote {} output to {}", args.format, path.display());
+        return Ok(());
+    }
+
+    if let Some(amplitude) = args.breathe {
+        return breathe(args, &m, amplitude, fill_char);
+    }
+
+    let result = if args.braille {
+        m.braille()?
+    } else if let Some(ramp) = &args.ramp {
+        m.ramp(&ramp.chars().collect::<Vec<_>>())?
+    } else {
+        m.convert()?
+    }
+    .with_margin(args.margin, fill_char);
+    for line in result.lines() {
         log::debug!("{line}");
     }
 
-    View::animate(&s)?;
-    log::info!("Animation completed successfully!");
+    let lines = affix_lines(result.lines(), &args.line_prefix, &args.line_suffix);
+    if args.plain {
+        View::print(&lines, args.line_ending)?;
+    } else {
+        animate(args, &m, &result, &lines, length)?;
+        log::info!("Animation completed successfully!");
+    }
+
+    if args.debug_grid {
+        eprint!("{}", result.debug_grid());
+    }
+
+    if args.report_unused_glyphs {
+        let unused = result.unused_characters(&chars);
+        eprintln!(
+            "{} of {} typeset characters were never used:",
+            unused.len(),
+            chars.len()
+        );
+        eprintln!("{}", unused.iter().collect::<String>());
+    }
+
+    Ok(())
+}
+
+/// Converts a synthetic left-to-right gradient with the bundled typeset and font,
+/// then checks basic invariants of the result, printing PASS or FAIL for each.
+fn selftest() -> Result<()> {
+    let (width, height) = SELFTEST_IMAGE_SIZE;
+    // dark on the left, light on the right.
+    let image = DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, _| {
+        image::Luma([(x * 255 / (width - 1)) as u8])
+    }));
+    let mut m = Model::new(MIN_LENGTH, &image, &default_typeset(), FONT_DATA)?;
+    let result = m.convert()?;
+    let (columns, rows) = (result.columns() as usize, result.rows() as usize);
+
+    let dimensions = rows > 0
+        && result.lines().len() == rows
+        && result.lines().iter().all(|l| l.chars().count() == columns);
+
+    let coverage = result.scores().iter().any(Option::is_some);
+
+    // the ink of each column should fall from the dark to the light side.
+    let scale = PxScale::from(Config::default().glyph_scale);
+    let mut ink = vec![0.0; columns];
+    for line in result.lines() {
+        for (x, c) in line.chars().enumerate().take(columns) {
+            ink[x] += typistapp::element::glyph_ink(m.font(), c, scale)? / rows as f32;
+        }
+    }
+    let quarter = (columns / 4).max(1);
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    let gradient = columns > 0 && mean(&ink[..quarter]) > mean(&ink[columns - quarter..]);
+
+    let checks = [
+        ("output dimensions match the grid", dimensions),
+        ("tiles are covered by glyphs", coverage),
+        ("ink follows the gradient", gradient),
+    ];
+    for (name, passed) in checks {
+        println!("{} {name}", if passed { "PASS" } else { "FAIL" });
+    }
+    if checks.iter().any(|(_, passed)| !passed) {
+        bail!("Self-test failed");
+    }
+
+    Ok(())
+}
+
+/// Converts the input video frame by frame and writes `frame_NNNNN.txt` files into `dir`.
+#[cfg(feature = "video")]
+fn write_frames(
+    args: &Args,
+    chars: &[char],
+    weights: &HashMap<char, f64>,
+    length: u32,
+    path: &str,
+    dir: &std::path::Path,
+) -> Result<()> {
+    // Removed when dropped, so extracted frames don't outlive a failed conversion.
+    let work_dir = tempfile::Builder::new()
+        .prefix("typistapp-frames-")
+        .tempdir()?;
+    let frames = typistapp::video::extract_frames(std::path::Path::new(path), work_dir.path())?;
+
+    let config = config(args, weights);
+    let results = typistapp::video::convert_frames(&frames, length, chars, FONT_DATA, &config)?;
+    work_dir.close()?;
+
+    std::fs::create_dir_all(dir)?;
+    for (i, result) in results.into_iter().enumerate() {
+        let result = result.with_margin(args.margin, config.fill_char);
+        let path = dir.join(format!("frame_{:05}.txt", i + 1));
+        let lines = affix_lines(result.lines(), &args.line_prefix, &args.line_suffix);
+        std::fs::write(&path, lines.join(args.line_ending.as_str()))
+            .with_context(|| format!("Failed to write frame: {}", path.display()))?;
+    }
+    log::info!("Wrote frames to {}", dir.display());
+
+    Ok(())
+}
+
+/// Converts a 2D NumPy array of luminance values, bypassing image decoding, and shows the art.
+#[cfg(feature = "npy")]
+fn convert_npy(
+    args: &Args,
+    chars: &[char],
+    weights: &HashMap<char, f64>,
+    path: &str,
+) -> Result<()> {
+    if args.mask.is_some() {
+        bail!("--mask applies to image input only, not to .npy arrays");
+    }
+    let length = output_length(args, None)?;
+    let array = typistapp::npy::LuminanceArray::load(path)
+        .with_context(|| format!("Failed to load array: {path}"))?;
+    log::debug!(
+        "Array loaded: {path} ({}x{})",
+        array.width(),
+        array.height()
+    );
+
+    let config = config(args, weights);
+    let fill_char = config.fill_char;
+    let result = array
+        .convert(length, chars, FONT_DATA, config)?
+        .with_margin(args.margin, fill_char);
+    let lines = affix_lines(result.lines(), &args.line_prefix, &args.line_suffix);
+    if args.plain {
+        View::print(&lines, args.line_ending)?;
+    } else {
+        view(args)?.animate(&lines)?;
+    }
+
+    Ok(())
+}
+
+/// Animates the text of the file at `path`, or of stdin for `-`, without converting anything.
+fn type_text(args: &Args, path: &str) -> Result<()> {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read text from stdin")?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read text: {path}"))?
+    };
+    let lines = affix_lines(
+        &View::text_lines(&text),
+        &args.line_prefix,
+        &args.line_suffix,
+    );
+
+    if args.plain {
+        View::print(&lines, args.line_ending)?;
+        return Ok(());
+    }
+    view(args)?.animate(&lines)?;
+
+    Ok(())
+}
+
+/// Loads the numbered art files in `dir` and plays them as an animation.
+fn play(args: &Args, dir: &std::path::Path) -> Result<()> {
+    let mut paths: Vec<std::path::PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read frame directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        bail!("No frame files (*.txt) found in {}", dir.display());
+    }
+
+    let mut frames = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read frame: {}", path.display()))?;
+        frames.push(text.lines().map(str::to_string).collect::<Vec<_>>());
+    }
+    log::info!("Loaded {} frames from {}", frames.len(), dir.display());
+
+    view(args)?.play_frames(&frames, args.fps)?;
+
+    Ok(())
+}
+
+/// Converts the image at a cycle of luminance offsets and loops the frames
+/// at `--fps` until a quit key is pressed.
+fn breathe(args: &Args, m: &Model, amplitude: f64, fill_char: char) -> Result<()> {
+    // NOTE: at least two frames, so there is something to oscillate between.
+    let count = ((args.fps * BREATHE_PERIOD_SECS).ceil() as usize).max(2);
+    let frames: Vec<Vec<String>> = m
+        .breathe(amplitude, count)?
+        .into_iter()
+        .map(|result| {
+            let result = result.with_margin(args.margin, fill_char);
+            affix_lines(result.lines(), &args.line_prefix, &args.line_suffix)
+        })
+        .collect();
+    log::info!("Breathing with {count} frames of amplitude {amplitude}");
+
+    view(args)?.loop_frames(&frames, args.fps)?;
 
     Ok(())
 }
+
+/// Animates the affixed `lines` of `result` in the terminal with the styling
+/// options of `args`, below a preview of the source with `--compare`.
+fn animate(
+    args: &Args,
+    m: &Model,
+    result: &ConversionResult,
+    lines: &[String],
+    length: u32,
+) -> Result<()> {
+    let mut view = view(args)?;
+    if args.bg_color {
+        view.bg = cell_backgrounds(
+            &m.tile_colors(),
+            result.lines(),
+            args.margin,
+            args.line_prefix.chars().count(),
+        );
+    }
+    if args.compare {
+        let preview = m.shade_preview()?;
+        execute!(
+            std::io::stdout(),
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        for line in &preview {
+            println!("{line}");
+        }
+        println!("{}", "─".repeat(length as usize * 2));
+        view.top = preview.len() as u16 + 1;
+    }
+    view.animate(lines)?;
+
+    Ok(())
+}
+
+/// Surrounds every line with `prefix` and `suffix`.
+fn affix_lines(lines: &[String], prefix: &str, suffix: &str) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| format!("{prefix}{line}{suffix}"))
+        .collect()
+}
+
+/// Lays the average tile colors out per character of the art, leaving the margin
+/// and the `prefix` characters before every row uncolored. Rows with more
+/// characters than tiles (Braille) spread each tile color over several characters.
+fn cell_backgrounds(
+    colors: &[[u8; 3]],
+    lines: &[String],
+    margin: u32,
+    prefix: usize,
+) -> Vec<Vec<Option<Color>>> {
+    let margin = margin as usize;
+    let rows = lines.len().saturating_sub(margin * 2);
+    let columns = colors.len() / rows.max(1);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(y, line)| {
+            let mut cells = vec![None; prefix];
+            let width = line.chars().count();
+            if y < margin || y >= margin + rows {
+                return cells;
+            }
+            let inner = width.saturating_sub(margin * 2).max(1);
+            cells.extend((0..width).map(|x| {
+                if x < margin || x >= width - margin {
+                    return None;
+                }
+                let tile = (y - margin) * columns + (x - margin) * columns / inner;
+                colors.get(tile).map(|&[r, g, b]| Color::Rgb { r, g, b })
+            }));
+            cells
+        })
+        .collect()
+}
+
+/// Derives a seed from the current time, for runs without `--seed`.
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Resolves the output width from LENGTH, the terminal width, or the character
+/// budget, which needs the dimensions of a still `image`.
+fn output_length(args: &Args, image: Option<&DynamicImage>) -> Result<u32> {
+    if let Some(length) = args.length {
+        return Ok(length);
+    }
+    if args.fit_terminal {
+        return terminal_length();
+    }
+    let Some(budget) = args.char_budget else {
+        bail!("LENGTH is required");
+    };
+    let image = image.context("--char-budget needs a still image")?;
+    match Model::length_for_budget(image.width(), image.height(), budget, args.retain_ratio) {
+        Some(length) => {
+            log::info!("Length {length} fits the budget of {budget} characters");
+            Ok(length)
+        }
+        None => {
+            log::warn!(
+                "Even the minimum length {MIN_LENGTH} exceeds the budget of {budget} characters"
+            );
+            Ok(MIN_LENGTH)
+        }
+    }
+}
+
+/// Derives an output width that fits the terminal, where each glyph takes two columns.
+fn terminal_length() -> Result<u32> {
+    let (columns, _) = terminal::size().context("Failed to query the terminal size")?;
+    let length = columns as u32 / 2;
+    if length < MIN_LENGTH {
+        log::warn!(
+            "Terminal is {columns} columns wide, narrower than {MIN_LENGTH} glyphs; using {MIN_LENGTH}"
+        );
+    }
+    let length = length.clamp(MIN_LENGTH, MAX_LENGTH);
+    log::info!("Fitting {length} glyphs into {columns} terminal columns");
+
+    Ok(length)
+}
+
+/// Builds the view of the art from the reveal, color, and timing options of `args`.
+fn view(args: &Args) -> Result<View> {
+    Ok(View {
+        reveal: args.reveal,
+        fg: args.fg,
+        attr: args.attr,
+        order: args.reveal_order,
+        seed: args.seed.unwrap_or_else(time_seed),
+        beats: beats(args)?,
+        delay: args.delay_ms.map(Duration::from_millis),
+        ..Default::default()
+    })
+}
+
+/// Reads the `--beat-file` timestamps: one whole number of milliseconds per
+/// line, blank lines skipped, returned in ascending order. Empty without a file.
+fn beats(args: &Args) -> Result<Vec<u64>> {
+    let Some(path) = &args.beat_file else {
+        return Ok(Vec::new());
+    };
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read beat file: {}", path.display()))?;
+
+    let mut beats = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let beat = line.parse::<u64>().with_context(|| {
+            format!(
+                "Invalid timestamp {line:?} on line {} of {}",
+                i + 1,
+                path.display()
+            )
+        })?;
+        beats.push(beat);
+    }
+    beats.sort_unstable();
+    log::debug!("Loaded {} beats from {}", beats.len(), path.display());
+
+    Ok(beats)
+}
+
+/// Returns the typeset characters and their usage weights: those of the
+/// `--typeset` file if given, otherwise the embedded typeset with no weights.
+fn typeset(args: &Args) -> Result<(Vec<char>, HashMap<char, f64>)> {
+    let Some(path) = &args.typeset else {
+        return Ok((default_typeset(), HashMap::new()));
+    };
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read typeset: {}", path.display()))?;
+    let (chars, weights) = parse_typeset(&text)
+        .with_context(|| format!("Failed to parse typeset: {}", path.display()))?;
+    if chars.is_empty() {
+        bail!("Typeset file has no characters: {}", path.display());
+    }
+
+    Ok((chars, weights))
+}
+
+/// Builds the conversion configuration from the command-line arguments and
+/// the usage weights of the typeset.
+fn config(args: &Args, glyph_weights: &HashMap<char, f64>) -> Config {
+    let defaults = Config::default();
+    Config {
+        sample: args.sample,
+        glyph_scale: args.glyph_scale.unwrap_or(defaults.glyph_scale),
+        glyph_margin: args.glyph_margin.unwrap_or(defaults.glyph_margin),
+        sort: args.sort,
+        unstable_sort: args.no_sort_stability,
+        match_resolution: args.match_resolution.map(|n| n as usize),
+        luminance_band: args.luminance_band,
+        local_contrast: args.local_contrast,
+        glyph_weights: glyph_weights.clone(),
+        glyph_denylist: args
+            .glyph_denylist
+            .as_deref()
+            .map(|chars| chars.chars().collect())
+            .unwrap_or_default(),
+        metric_topk: args.metric_topk.map(|k| k as usize),
+        retain_ratio: args.retain_ratio,
+        outline: args.outline,
+        invert_luminance: args.invert_luminance,
+        pivot: args.pivot,
+        strict: args.strict,
+        channel_weights: args
+            .channel_weights
+            .unwrap_or(args.colorspace.coefficients()),
+        luma: args.luma,
+        alpha_as_lightness: args.alpha_as_lightness,
+        supersample: args.supersample,
+        subpixel_coverage: args.subpixel_coverage,
+        align: args.align,
+        fill_char: args.fill_char.unwrap_or(defaults.fill_char),
+        tile_filter: args.tile_filter,
+        two_stage_resize: args.two_stage_resize,
+        collapse_case: args.collapse_case,
+        proportional: args.proportional,
+        match_cache: args.match_cache,
+        metric: args.metric.similarity(),
+        ..defaults
+    }
+}
+
+/// Parses a hex color (see [`parse_rgba`]) into a terminal color, ignoring its alpha.
+fn parse_hex_color(s: &str) -> std::result::Result<Color, String> {
+    let [r, g, b, _] = parse_rgba(s)?;
+    Ok(Color::Rgb { r, g, b })
+}
+
+/// Parses a `#rgb`, `#rrggbb`, or `#rrggbbaa` hex color, with or without the `#`.
+fn parse_rgba(s: &str) -> std::result::Result<[u8; 4], String> {
+    typistapp::color::Color::from_hex(s).map_err(|e| e.to_string())
+}
+
+/// Parses `--luminance-band` as a finite, non-negative luminance distance.
+fn parse_luminance_band(s: &str) -> std::result::Result<f64, String> {
+    let band: f64 = s.parse().map_err(|e| format!("{s:?}: {e}"))?;
+    if !band.is_finite() || band < 0.0 {
+        return Err("the band must be a non-negative number".to_string());
+    }
+
+    Ok(band)
+}
+
+/// Parses `--breathe` as a luminance amplitude within [0, 1].
+fn parse_amplitude(s: &str) -> std::result::Result<f64, String> {
+    let amplitude: f64 = s.parse().map_err(|e| format!("{s:?}: {e}"))?;
+    if !(0.0..=1.0).contains(&amplitude) {
+        return Err("the amplitude must be between 0 and 1".to_string());
+    }
+
+    Ok(amplitude)
+}
+
+/// Parses `--pivot` as a tone within [0, 1].
+fn parse_pivot(s: &str) -> std::result::Result<f64, String> {
+    let pivot: f64 = s.parse().map_err(|e| format!("{s:?}: {e}"))?;
+    if !(0.0..=1.0).contains(&pivot) {
+        return Err("the pivot must be between 0 and 1".to_string());
+    }
+
+    Ok(pivot)
+}
+
+/// Parses `--outline` as a finite, non-negative strength.
+fn parse_outline(s: &str) -> std::result::Result<f32, String> {
+    let strength: f32 = s.parse().map_err(|e| format!("{s:?}: {e}"))?;
+    if !strength.is_finite() || strength < 0.0 {
+        return Err("the strength must be a non-negative number".to_string());
+    }
+
+    Ok(strength)
+}
+
+/// Parses `--channel-weights` from three comma-separated, non-negative numbers.
+fn parse_channel_weights(s: &str) -> std::result::Result<[f64; 3], String> {
+    let weights: Vec<f64> = s
+        .split(',')
+        .map(|w| w.trim().parse::<f64>().map_err(|e| format!("{w:?}: {e}")))
+        .collect::<std::result::Result<_, _>>()?;
+    let weights: [f64; 3] = weights
+        .try_into()
+        .map_err(|_| "expected three comma-separated weights".to_string())?;
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) || weights.iter().sum::<f64>() <= 0.0 {
+        return Err("weights must be non-negative and not all zero".to_string());
+    }
+
+    Ok(weights)
+}
+
+/// Loads the `--mask` image at `path` in grayscale and crops it to the part of
+/// `source` selected by `--region`, scaled to the mask's own size.
+fn importance_mask(
+    args: &Args,
+    path: &std::path::Path,
+    source: &DynamicImage,
+) -> Result<GrayImage> {
+    let mask = image::open(path)
+        .with_context(|| format!("Failed to open mask: {}", path.display()))?
+        .to_luma8();
+    let (scale_x, scale_y) = (
+        mask.width() as f64 / source.width() as f64,
+        mask.height() as f64 / source.height() as f64,
+    );
+    if (scale_x / scale_y - 1.0).abs() > 0.01 {
+        log::warn!(
+            "The {}x{} mask is shaped differently from the {}x{} source; it is stretched to fit",
+            mask.width(),
+            mask.height(),
+            source.width(),
+            source.height()
+        );
+    }
+
+    let Some([x, y, width, height]) = args.region else {
+        return Ok(mask);
+    };
+    let scale = |value: u32, by: f64| (value as f64 * by).round() as u32;
+    let (x, y) = (scale(x, scale_x), scale(y, scale_y));
+    let width = scale(width, scale_x).clamp(1, mask.width().saturating_sub(x).max(1));
+    let height = scale(height, scale_y).clamp(1, mask.height().saturating_sub(y).max(1));
+    Ok(image::imageops::crop_imm(&mask, x, y, width, height).to_image())
+}
+
+/// Parses `--region` from four comma-separated pixel values: x, y, width, and height.
+fn parse_region(s: &str) -> std::result::Result<[u32; 4], String> {
+    let values: Vec<u32> = s
+        .split(',')
+        .map(|v| v.trim().parse::<u32>().map_err(|e| format!("{v:?}: {e}")))
+        .collect::<std::result::Result<_, _>>()?;
+
+    values
+        .try_into()
+        .map_err(|_| "expected four comma-separated values: x,y,w,h".to_string())
+}
+
+/// Loads an image from a local path, or from a URL when it starts with `http://` or `https://`.
+fn load_image(path: &str) -> Result<DynamicImage> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return fetch_image(path);
+    }
+
+    image::open(path).with_context(|| format!("Failed to open image: {path}"))
+}
+
+/// Downloads an image from the given URL and decodes it from memory.
+#[cfg(feature = "network")]
+fn fetch_image(url: &str) -> Result<DynamicImage> {
+    let mut response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch image: {url}"))?;
+
+    if let Some(content_type) = response.headers().get("content-type")
+        && !content_type.as_bytes().starts_with(b"image/")
+    {
+        bail!(
+            "URL did not return an image (content-type: {}): {url}",
+            String::from_utf8_lossy(content_type.as_bytes())
+        );
+    }
+
+    let bytes = response
+        .body_mut()
+        .read_to_vec()
+        .with_context(|| format!("Failed to read response body: {url}"))?;
+    image::load_from_memory(&bytes).with_context(|| format!("Failed to decode image from: {url}"))
+}
+
+/// Reports that URL inputs need the `network` feature.
+#[cfg(not(feature = "network"))]
+fn fetch_image(url: &str) -> Result<DynamicImage> {
+    bail!("Cannot fetch {url}: typistapp was built without the `network` feature")
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::F64_ALMOST_ZERO;
+use crate::correlation::correlation;
+
+/// Scores how similar a candidate glyph's characteristics are to a tile's.
+///
+/// The built-in metrics return `None` for inputs with non-finite values,
+/// so the matcher skips such candidates instead of comparing against NaN.
+///
+/// Implement this to plug custom matching logic into the conversion
+/// through [`Config::metric`](crate::config::Config::metric).
+pub trait SimilarityMetric: fmt::Debug + Send + Sync {
+    /// Returns the score of `candidate` against `target`, or `None` if they
+    /// cannot be compared (for example, because their lengths differ).
+    fn score(&self, target: &[f64], candidate: &[f64]) -> Option<f64>;
+
+    /// Whether a higher score means a better match.
+    fn higher_is_better(&self) -> bool;
+}
+
+/// The Pearson correlation coefficient, in [-1, 1]. Compares the shape of the
+/// characteristics regardless of their overall brightness.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pearson;
+
+impl SimilarityMetric for Pearson {
+    fn score(&self, target: &[f64], candidate: &[f64]) -> Option<f64> {
+        correlation(target, candidate)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// The cosine of the angle between the characteristics, in [0, 1] for
+/// non-negative values. Unlike Pearson, it does not subtract the means.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cosine;
+
+impl SimilarityMetric for Cosine {
+    fn score(&self, target: &[f64], candidate: &[f64]) -> Option<f64> {
+        if !comparable(target, candidate) {
+            return None;
+        }
+
+        let dot: f64 = target.iter().zip(candidate).map(|(x, y)| x * y).sum();
+        let norm_x = target.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let norm_y = candidate.iter().map(|y| y * y).sum::<f64>().sqrt();
+        let denominator = norm_x * norm_y;
+        if denominator < F64_ALMOST_ZERO {
+            // two all-black vectors are identical; one alone matches nothing.
+            return Some(if norm_x < F64_ALMOST_ZERO && norm_y < F64_ALMOST_ZERO {
+                1.0
+            } else {
+                0.0
+            });
+        }
+
+        Some(dot / denominator)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// The mean absolute difference of the characteristics, in [0, 1].
+/// Lower is better; it compares tone as well as shape.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct L1;
+
+impl SimilarityMetric for L1 {
+    fn score(&self, target: &[f64], candidate: &[f64]) -> Option<f64> {
+        if !comparable(target, candidate) {
+            return None;
+        }
+
+        let sum: f64 = target
+            .iter()
+            .zip(candidate)
+            .map(|(x, y)| (x - y).abs())
+            .sum();
+        Some(sum / target.len() as f64)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Whether two characteristics have the same, non-zero length and only finite values.
+fn comparable(target: &[f64], candidate: &[f64]) -> bool {
+    target.len() == candidate.len()
+        && !target.is_empty()
+        && target.iter().chain(candidate).all(|v| v.is_finite())
+}
+
+/// The built-in similarity metrics, selectable by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Metric {
+    /// Pearson correlation.
+    #[default]
+    Pearson,
+    /// Cosine similarity.
+    Cosine,
+    /// Mean absolute difference.
+    L1,
+}
+
+impl Metric {
+    /// Returns the implementation of this metric.
+    pub fn similarity(self) -> Arc<dyn SimilarityMetric> {
+        match self {
+            Metric::Pearson => Arc::new(Pearson),
+            Metric::Cosine => Arc::new(Cosine),
+            Metric::L1 => Arc::new(L1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_ignores_scale() {
+        let score = Cosine.score(&[1.0, 2.0, 0.0], &[2.0, 4.0, 0.0]).unwrap();
+        assert!((score - 1.0).abs() < 1e-9);
+        assert_eq!(Cosine.score(&[1.0, 0.0], &[0.0, 1.0]), Some(0.0));
+        assert_eq!(Cosine.score(&[0.0, 0.0], &[0.0, 0.0]), Some(1.0));
+    }
+
+    #[test]
+    fn l1_is_mean_absolute_difference() {
+        assert_eq!(L1.score(&[0.0, 1.0], &[0.5, 0.5]), Some(0.5));
+        assert_eq!(L1.score(&[0.25, 0.75], &[0.25, 0.75]), Some(0.0));
+        assert!(!L1.higher_is_better());
+    }
+
+    #[test]
+    fn metrics_reject_mismatched_lengths() {
+        for metric in [Metric::Pearson, Metric::Cosine, Metric::L1] {
+            assert_eq!(metric.similarity().score(&[1.0], &[1.0, 2.0]), None);
+            assert_eq!(metric.similarity().score(&[], &[]), None);
+            assert_eq!(metric.similarity().score(&[f64::NAN], &[1.0]), None);
+        }
+    }
+}
@@ -1,12 +1,117 @@
-use ab_glyph::FontArc;
-use anyhow::{Result, bail};
-use image::{DynamicImage, imageops};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage, imageops};
 use log;
 use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
 
-use crate::correlation::correlation;
+use crate::config::Config;
+use crate::conversion::ConversionResult;
 use crate::element::Element;
-use crate::{FULL_WIDTH_SPACE, GLYPH_SCALE, IMAGE_SIZE, NUM_OF_CANDIDATES};
+use crate::error::{Result, TypistError};
+use crate::typesetter::{LuminanceRange, Typesetter};
+use crate::view::char_width;
+use crate::{
+    BRAILLE_BLANK, BRAILLE_DOT_BITS, F64_ALMOST_ZERO, IMAGE_SIZE, MAX_LENGTH, MIN_LENGTH,
+    SHADE_RAMP,
+};
+
+/// The intermediate size of the two-stage resize, as a multiple of the target size.
+const TWO_STAGE_FACTOR: u32 = 2;
+
+/// How close to black or white [`Model::breathe`] may move mid-gray, so its
+/// tone curve stays defined for amplitudes up to 1.
+const BREATHE_MIDTONE_MARGIN: f64 = 0.02;
+
+/// The order in which the rendered typeset elements are arranged before matching.
+///
+/// Matching relies on a luminance-sorted typeset for its binary search. With `Ink`
+/// or `None`, the search falls back to a linear scan over the whole typeset to pick
+/// the tonally closest candidates, which is slower but independent of the order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortOrder {
+    /// Ascending luminance (lightest last).
+    #[default]
+    Luminance,
+    /// Ascending ink density, i.e. the sum of the inverted characteristics.
+    Ink,
+    /// Keeps the order of the characters as given.
+    None,
+}
+
+/// The filter used to resize the source so that every tile spans one glyph cell of pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TileFilter {
+    /// Nearest neighbor; keeps hard edges but aliases.
+    Nearest,
+    /// Linear interpolation.
+    #[default]
+    Triangle,
+    /// Cubic interpolation.
+    CatmullRom,
+    /// Gaussian blur; the softest result.
+    Gaussian,
+    /// Lanczos with a window of 3; sharp and smooth, but the slowest.
+    Lanczos3,
+}
+
+impl TileFilter {
+    /// Returns the corresponding `image` filter.
+    fn filter_type(self) -> imageops::FilterType {
+        match self {
+            TileFilter::Nearest => imageops::FilterType::Nearest,
+            TileFilter::Triangle => imageops::FilterType::Triangle,
+            TileFilter::CatmullRom => imageops::FilterType::CatmullRom,
+            TileFilter::Gaussian => imageops::FilterType::Gaussian,
+            TileFilter::Lanczos3 => imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// A summary of what a conversion would work with, computed without matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionPlan {
+    /// The size of the resized (and possibly padded) image, in pixels.
+    pub width: u32,
+    pub height: u32,
+
+    /// The number of characters per line and the number of lines.
+    pub columns: u32,
+    pub lines: u32,
+
+    /// The number of rendered typeset elements.
+    pub typeset_elements: usize,
+
+    /// The raw luminance range of the picture tiles and of the typeset glyphs.
+    pub picture_range: (f64, f64),
+    pub typeset_range: (f64, f64),
+}
+
+impl fmt::Display for ConversionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Image: {}x{} px", self.width, self.height)?;
+        writeln!(
+            f,
+            "Grid: {} columns x {} lines ({} tiles)",
+            self.columns,
+            self.lines,
+            self.columns * self.lines
+        )?;
+        writeln!(f, "Typeset elements: {}", self.typeset_elements)?;
+        writeln!(
+            f,
+            "Picture luminance range: [{:.3}, {:.3}]",
+            self.picture_range.0, self.picture_range.1
+        )?;
+        write!(
+            f,
+            "Typeset luminance range: [{:.3}, {:.3}]",
+            self.typeset_range.0, self.typeset_range.1
+        )
+    }
+}
 
 /// A struct that serves as the Model (M) in MVC. Specializes in data management.
 /// Converts an image into typist-art using a set of full-width characters and a font.
@@ -15,17 +120,14 @@ pub struct Model {
     /// The source image to be converted to typist-art.
     image: DynamicImage,
 
-    /// A collection of full-width characters used for rendering the art.
-    characters: Vec<char>,
-
-    /// The font used to render each character.
-    font: FontArc,
-
     /// The number of characters (columns) per line in the output art.
     columns: u32,
 
     /// The total number of lines (rows) in the output art.
     lines: u32,
+
+    /// The characters, font, and configuration that render and match the typeset.
+    typesetter: Typesetter,
 }
 
 impl Model {
@@ -36,289 +138,1696 @@ impl Model {
         characters: &[char],
         font: &[u8],
     ) -> Result<Self> {
+        Self::with_config(length, image, characters, font, Config::default())
+    }
+
+    /// Creates a new Model instance like [`Model::new`], reading the font from a file.
+    pub fn with_font_path<P: AsRef<Path>>(
+        length: u32,
+        image: &DynamicImage,
+        characters: &[char],
+        path: P,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let font = std::fs::read(path).map_err(|source| TypistError::FontFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::new(length, image, characters, &font)
+    }
+
+    /// Creates a new Model instance like [`Model::new`], using the given configuration.
+    pub fn with_config(
+        length: u32,
+        image: &DynamicImage,
+        characters: &[char],
+        font: &[u8],
+        config: Config,
+    ) -> Result<Self> {
+        if characters.is_empty() {
+            return Err(TypistError::EmptyTypeset);
+        }
+
         let columns = length;
-        let width = IMAGE_SIZE * columns;
-        let height = image.height() * width / image.width();
-        let img = image.resize(width, height, imageops::FilterType::Triangle);
-        let lines = height / IMAGE_SIZE;
-        log::info!(
-            "Image dimensions: {width}x{height}, size: {IMAGE_SIZE}, columns: {columns}, lines: {lines}",
-        );
-        let font = match FontArc::try_from_vec(font.to_vec()) {
-            Ok(f) => f,
-            Err(e) => bail!("Failed to load font: {}", e),
-        };
+        let (img, lines) = Self::prepare_image(image, columns, &config)?;
+        let font = FontArc::try_from_vec(font.to_vec())?;
 
         Ok(Model {
             image: img,
-            characters: characters.to_vec(),
-            font,
             columns,
             lines,
+            typesetter: Typesetter::new(characters, font, config),
         })
     }
 
-    /// Converts the input image into a vector of typist-art strings.
-    pub fn convert(&mut self) -> Result<Vec<String>> {
-        let typeset_elements = self.typeset_elements(&self.characters)?;
-        let picture_elements =
-            self.picture_elements(&self.image, IMAGE_SIZE, self.columns, self.lines)?;
+    /// Validates the output width, then resizes the image to `columns` tiles per row,
+    /// applying the configured edge enhancement and bottom padding.
+    /// An image too short for a single row of tiles is padded to one row.
+    /// Returns the prepared image and its number of lines.
+    pub(crate) fn prepare_image(
+        image: &DynamicImage,
+        columns: u32,
+        config: &Config,
+    ) -> Result<(DynamicImage, u32)> {
+        if columns == 0 {
+            return Err(TypistError::InvalidLength(columns));
+        }
+        if !(MIN_LENGTH..=MAX_LENGTH).contains(&columns) {
+            log::warn!(
+                "Length {columns} is outside the recommended range {MIN_LENGTH}..={MAX_LENGTH}"
+            );
+        }
+        if image.width() == 0 || image.height() == 0 {
+            return Err(TypistError::EmptyImage);
+        }
+
+        let cell = config.cell_size();
+        let (width, height, mut lines) = Self::dimensions(
+            image.width(),
+            image.height(),
+            columns,
+            cell,
+            config.retain_ratio,
+        );
+        if lines == 0 {
+            log::warn!(
+                "The {}x{} image is too short for a row of tiles at length {columns}; padding it to 1 row",
+                image.width(),
+                image.height(),
+            );
+            lines = 1;
+        }
+        let mut img = if config.two_stage_resize
+            && image.width() > width * TWO_STAGE_FACTOR
+            && image.height() > height * TWO_STAGE_FACTOR
+        {
+            // a fast area-averaging pass to twice the target, so the quality filter
+            // only sees a small image with its fine detail already averaged out.
+            image
+                .thumbnail_exact(width * TWO_STAGE_FACTOR, height * TWO_STAGE_FACTOR)
+                .resize_exact(width, height, config.tile_filter.filter_type())
+        } else {
+            image.resize(width, height, config.tile_filter.filter_type())
+        };
+        if config.outline > 0.0 {
+            img = Self::enhance_edges(&img, config.outline);
+        }
+        if lines * cell > img.height() {
+            // pad the partial bottom tile with a white background.
+            let mut canvas = DynamicImage::from(RgbaImage::from_pixel(
+                width,
+                lines * cell,
+                Rgba([255, 255, 255, 255]),
+            ));
+            imageops::overlay(&mut canvas, &img, 0, 0);
+            img = canvas;
+        }
         log::info!(
-            "Typeset elements: {}, Picture elements: {}",
-            typeset_elements.len(),
-            picture_elements.len()
+            "Image dimensions: {width}x{height}, size: {cell}, columns: {columns}, lines: {lines}",
         );
 
-        let typist_art_elements = Self::generate_typist_art(&picture_elements, &typeset_elements);
-        log::info!("Converted picture elements to typist art.");
+        Ok((img, lines))
+    }
 
-        let mut result = vec![];
-        let mut v = vec![];
-        for (i, e) in typist_art_elements.iter().enumerate() {
-            if i % self.columns as usize == 0 && i != 0 {
-                result.push(v.iter().collect());
-                v.clear();
+    /// Sharpens edges by subtracting `strength` times the 4-neighbor Laplacian
+    /// from every color channel, clamping at the image borders. Alpha is kept.
+    fn enhance_edges(image: &DynamicImage, strength: f32) -> DynamicImage {
+        let source = image.to_rgba8();
+        let (width, height) = source.dimensions();
+        let output = RgbaImage::from_fn(width, height, |x, y| {
+            let neighbors = [
+                source.get_pixel(x.saturating_sub(1), y),
+                source.get_pixel((x + 1).min(width - 1), y),
+                source.get_pixel(x, y.saturating_sub(1)),
+                source.get_pixel(x, (y + 1).min(height - 1)),
+            ];
+            let center = source.get_pixel(x, y);
+            let mut pixel = *center;
+            for c in 0..3 {
+                let sum: f32 = neighbors.iter().map(|p| p[c] as f32).sum();
+                let laplacian = 4.0 * center[c] as f32 - sum;
+                pixel[c] = (center[c] as f32 + strength * laplacian)
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
             }
-            v.push(e.character().unwrap_or(FULL_WIDTH_SPACE));
-        }
-        if !v.is_empty() {
-            result.push(v.iter().collect());
+            pixel
+        });
+
+        DynamicImage::ImageRgba8(output)
+    }
+
+    /// Crops `image` to the `width`×`height` rectangle whose top-left corner is
+    /// at (`x`, `y`) in pixels, so the art focuses on that part of the source.
+    /// Fails if the region is empty or does not fit within the image.
+    pub fn crop_region(
+        image: &DynamicImage,
+        [x, y, width, height]: [u32; 4],
+    ) -> Result<DynamicImage> {
+        let (image_width, image_height) = image.dimensions();
+        let fits = |start: u32, size: u32, bound: u32| {
+            size > 0 && start.checked_add(size).is_some_and(|end| end <= bound)
+        };
+        if !fits(x, width, image_width) || !fits(y, height, image_height) {
+            return Err(TypistError::InvalidRegion {
+                x,
+                y,
+                width,
+                height,
+                image_width,
+                image_height,
+            });
         }
 
-        Ok(result)
+        Ok(image.crop_imm(x, y, width, height))
     }
 
-    /// Divides the input image into a grid of picture elements (tiles),
-    /// computes their luminance characteristics, and normalizes them.
-    fn picture_elements(
-        &self,
+    /// Pads `image` with bars of `background` above and below, just enough that
+    /// rendering it `columns` characters wide fills a whole number of tile rows
+    /// of `cell` pixels (see [`Config::cell_size`]).
+    /// Unlike the default, which crops the partial bottom tile, or `retain_ratio`,
+    /// which pads only that tile, every source pixel is kept and stays centered.
+    pub fn letterbox(
         image: &DynamicImage,
-        size: u32,
         columns: u32,
-        lines: u32,
-    ) -> Result<Vec<Element>> {
-        let mut elements = vec![];
-        for y in 0..lines {
-            for x in 0..columns {
-                let block_image = image.crop_imm(x * size, y * size, size, size);
-                elements.push(Element::from_image(block_image)?);
-            }
+        cell: u32,
+        background: Rgba<u8>,
+    ) -> DynamicImage {
+        let (image_width, image_height) = image.dimensions();
+        if columns == 0 || cell == 0 || image_width == 0 {
+            return image.clone();
         }
 
-        // normalize the luminance of the picture elements.
-        Self::normalize_elements(&mut elements)?;
+        let width = cell * columns;
+        // NOTE: a height that is a multiple of the image width always scales to whole
+        // tiles, so the search ends within `image_width` steps.
+        let height = (image_height..)
+            .find(|&h| (h as u64 * width as u64 / image_width as u64).is_multiple_of(cell as u64))
+            .expect("a multiple of the image width fits");
+        if height == image_height {
+            return image.clone();
+        }
+
+        let mut canvas = RgbaImage::from_pixel(image_width, height, background);
+        let top = (height - image_height) / 2;
+        imageops::overlay(&mut canvas, &image.to_rgba8(), 0, top as i64);
+
+        DynamicImage::ImageRgba8(canvas)
+    }
 
-        Ok(elements)
+    /// Returns the `(columns, lines)` of the art for an image of
+    /// `image_width`×`image_height` rendered `length` characters wide with the
+    /// default cell size, without loading or resizing anything.
+    pub fn estimate_dimensions(
+        image_width: u32,
+        image_height: u32,
+        length: u32,
+        retain_ratio: bool,
+    ) -> (u32, u32) {
+        let (_, _, lines) =
+            Self::dimensions(image_width, image_height, length, IMAGE_SIZE, retain_ratio);
+        (length, lines.max(1))
     }
 
-    /// Renders each character into an image using the given font, converts
-    /// them into elements, normalizes their luminance, and sorts them by brightness.
-    fn typeset_elements(&self, characters: &[char]) -> Result<Vec<Element>> {
-        let mut elements: Vec<Element> = characters
+    /// Returns the number of tiles, `columns * rows`, that converting `image`
+    /// `length` characters wide with this model's configuration produces,
+    /// rounded exactly as the conversion does. Unlike [`Model::estimate_dimensions`],
+    /// it honors the configured cell size. Returns 0 for a zero length or an empty image.
+    pub fn tile_count(&self, length: u32, image: &DynamicImage) -> u32 {
+        if length == 0 || image.width() == 0 || image.height() == 0 {
+            return 0;
+        }
+
+        let (_, _, lines) = Self::dimensions(
+            image.width(),
+            image.height(),
+            length,
+            self.config().cell_size(),
+            self.config().retain_ratio,
+        );
+        // NOTE: `prepare_image` pads an image too short for a row to one row.
+        length * lines.max(1)
+    }
+
+    /// Returns the largest length in `MIN_LENGTH..=MAX_LENGTH` whose art of
+    /// `columns * lines` characters fits within `budget`, or `None` if even
+    /// `MIN_LENGTH` exceeds it.
+    pub fn length_for_budget(
+        image_width: u32,
+        image_height: u32,
+        budget: u64,
+        retain_ratio: bool,
+    ) -> Option<u32> {
+        // NOTE: the character count grows with the length, so the first fit from the top is the largest.
+        (MIN_LENGTH..=MAX_LENGTH).rev().find(|&length| {
+            let (columns, lines) =
+                Self::estimate_dimensions(image_width, image_height, length, retain_ratio);
+            columns as u64 * lines as u64 <= budget
+        })
+    }
+
+    /// Computes the resized image width and height and the number of lines for
+    /// an image of `image_width`×`image_height` rendered `columns` characters
+    /// wide, in tiles of `cell`×`cell` pixels.
+    ///
+    /// By default the number of lines is rounded down, dropping a partial bottom tile.
+    /// With `retain_ratio`, it is rounded up so the partial tile is kept (and padded).
+    fn dimensions(
+        image_width: u32,
+        image_height: u32,
+        columns: u32,
+        cell: u32,
+        retain_ratio: bool,
+    ) -> (u32, u32, u32) {
+        let width = cell * columns;
+        // NOTE: at least 1 pixel, so an extremely wide image still resizes to something.
+        let height = (image_height as u64 * width as u64 / image_width as u64).max(1) as u32;
+        let lines = if retain_ratio {
+            height.div_ceil(cell)
+        } else {
+            height / cell
+        };
+
+        (width, height, lines)
+    }
+
+    /// Returns the font the typeset is rendered and matched with.
+    pub fn font(&self) -> &FontArc {
+        self.typesetter.font()
+    }
+
+    /// Returns the configuration of the conversion.
+    pub(crate) fn config(&self) -> &Config {
+        self.typesetter.config()
+    }
+
+    /// Converts the input image into typist-art, keeping the match score of each tile.
+    pub fn convert(&mut self) -> Result<ConversionResult> {
+        let typeset_elements = self.typesetter.glyphs()?;
+        self.typesetter.convert_with_typeset(
+            &self.image,
+            self.columns,
+            self.lines,
+            typeset_elements,
+        )
+    }
+
+    /// Converts `image` at each of the candidate `lengths` in parallel, sharing a
+    /// single rendered typeset, and returns the length whose art has the best mean
+    /// score (see [`ConversionResult::mean_score`]) together with that score.
+    pub fn auto_length(
+        image: &DynamicImage,
+        lengths: &[u32],
+        characters: &[char],
+        font: &[u8],
+        config: &Config,
+    ) -> Result<(u32, f64)> {
+        let Some(&first) = lengths.first() else {
+            return Err(TypistError::InvalidLength(0));
+        };
+        let model = Self::with_config(first, image, characters, font, config.clone())?;
+        let typeset_elements = model.typesetter.glyphs()?;
+
+        let scores = lengths
             .par_iter()
-            .map(|c| Element::from_char(&self.font, *c, *GLYPH_SCALE))
+            .map(|&length| {
+                let (img, lines) = Self::prepare_image(image, length, config)?;
+                let score = model
+                    .typesetter
+                    .convert_with_typeset(&img, length, lines, typeset_elements)?
+                    .mean_score()
+                    .unwrap_or(f64::NAN);
+                log::info!("Length {length} scores {score:.4} on average");
+                Ok((length, score))
+            })
             .collect::<Result<Vec<_>>>()?;
 
-        // normalize the luminance of the typeset elements.
-        Self::normalize_elements(&mut elements)?;
+        // NOTE: the earliest candidate wins ties, and lengths without a score never win.
+        let better = |a: f64, b: f64| {
+            b.is_nan()
+                || if config.metric.higher_is_better() {
+                    a > b
+                } else {
+                    a < b
+                }
+        };
+        let best = scores
+            .into_iter()
+            .reduce(|best, candidate| {
+                if better(candidate.1, best.1) {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .expect("at least one candidate");
 
-        // sort the typeset elements by luminance.
-        elements.sort_by(|a, b| {
-            a.luminance()
-                .partial_cmp(&b.luminance())
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        log::debug!("Sorted typeset elements by luminance.");
-        for e in &elements {
-            log::debug!(
-                "Character: {:?}, Luminance: {}",
-                e.character(),
-                e.luminance(),
-            );
-        }
+        Ok(best)
+    }
+
+    /// Converts the image into `frames` frames of art whose tile luminance
+    /// oscillates over one sine period, so that playing them in a loop makes the
+    /// art "breathe". Each frame bends the raw tones with a gamma curve that moves
+    /// mid-gray by up to `amplitude` before the tiles are normalized, so the
+    /// change survives normalization and shows with any metric. The first frame
+    /// is the plain conversion. The typeset and the tiles are prepared only once.
+    pub fn breathe(&self, amplitude: f64, frames: usize) -> Result<Vec<ConversionResult>> {
+        let typeset_elements = self
+            .typesetter
+            .typeset_elements(self.typesetter.characters())?;
+        let (picture_elements, _) = self.typesetter.extract_picture_elements(
+            &self.image,
+            self.config().cell_size(),
+            self.columns,
+            self.lines,
+        )?;
+
+        (0..frames)
+            .map(|frame| {
+                let phase = std::f64::consts::TAU * frame as f64 / frames as f64;
+                let midtone = (0.5 + amplitude * phase.sin())
+                    .clamp(BREATHE_MIDTONE_MARGIN, 1.0 - BREATHE_MIDTONE_MARGIN);
+                let gamma = midtone.ln() / 0.5f64.ln();
+
+                let mut elements = picture_elements.clone();
+                elements
+                    .par_iter_mut()
+                    .for_each(|e| e.gamma(gamma, self.config().sample));
+                let mut range = LuminanceRange::default();
+                elements.iter().for_each(|e| range.add(e.luminance()));
+                self.typesetter
+                    .normalize_picture_elements(&mut elements, range, self.columns)?;
+                self.typesetter.remap_for_matching(&mut elements);
 
-        Ok(elements)
+                self.typesetter
+                    .assemble(&elements, &typeset_elements, self.columns, self.lines)
+            })
+            .collect()
     }
 
-    /// Normalizes the luminance and pixel characteristics of each element
-    /// so that all values are within a common range.
-    fn normalize_elements(elements: &mut [Element]) -> Result<()> {
-        let mut min = f64::INFINITY;
-        let mut max = f64::NEG_INFINITY;
-        for e in elements.iter_mut() {
-            if e.luminance() < min {
-                min = e.luminance();
-            }
-            if e.luminance() > max {
-                max = e.luminance();
+    /// Returns the normalized picture elements (tiles) of the image in row-major
+    /// order, exactly as the matcher sees them, for inspecting their
+    /// characteristics and luminance distribution.
+    pub fn tiles(&self) -> Result<Vec<Element>> {
+        self.typesetter.picture_elements(
+            &self.image,
+            self.config().cell_size(),
+            self.columns,
+            self.lines,
+        )
+    }
+
+    /// Returns the `n` characters that best match each tile of [`Model::tiles`],
+    /// in row-major order, with their scores, best first (descending for metrics
+    /// where higher is better). The candidates of each tile are picked and ranked
+    /// exactly like in a conversion, honoring the luminance band, match resolution,
+    /// and importance mask, and usage weights affect the rank but not the reported
+    /// score. So the first entry of each tile is the character a conversion picks,
+    /// unless [`Config::match_cache`] reuses the match of another flat tile.
+    /// Tiles without any scorable candidate get an empty list.
+    ///
+    /// The tiles and the typeset are prepared once for all tiles, so rank them
+    /// in one call rather than tile by tile.
+    pub fn rank_all_candidates(&self, n: usize) -> Result<Vec<Vec<(char, f64)>>> {
+        let importance = self.typesetter.tile_importance(self.columns, self.lines);
+
+        Ok(self.typesetter.rank_elements(
+            &self.tiles()?,
+            self.typesetter.glyphs()?,
+            importance.as_deref(),
+            n,
+        ))
+    }
+
+    /// Returns the `n` characters that best match a single picture element,
+    /// with their scores, best first (descending for metrics where higher is
+    /// better). The candidates are picked and ranked like for a tile without an
+    /// importance mask, so `picture_element` should come from [`Model::tiles`].
+    /// The typeset is rendered on the first call and reused by later ones; if
+    /// it cannot be rendered, there are no candidates.
+    pub fn rank_candidates(&self, picture_element: &Element, n: usize) -> Vec<(char, f64)> {
+        let typeset = match self.typesetter.glyphs() {
+            Ok(typeset) => typeset,
+            Err(e) => {
+                log::warn!("Typeset cannot be rendered: {e}");
+                return Vec::new();
             }
-        }
-        log::info!("Luminance range: [{min}, {max}]");
+        };
 
-        elements
-            .par_iter_mut()
-            .for_each(|e| e.normalized(min, max).unwrap());
-        log::info!("Normalized elements.");
+        self.typesetter
+            .rank_elements(std::slice::from_ref(picture_element), typeset, None, n)
+            .pop()
+            .unwrap_or_default()
+    }
 
-        Ok(())
+    /// Returns the rendered, normalized, and ordered typeset elements
+    /// exactly as the matcher sees them, before any matching.
+    pub fn glyphs(&self) -> Result<Vec<Element>> {
+        Ok(self.typesetter.glyphs()?.to_vec())
     }
 
-    /// Finds the index of the element in the typeset list whose luminance is
-    /// closest to the given target luminance value.
-    fn closest_luminance_index(target: f64, typeset_elements: &[Element]) -> usize {
-        let result = typeset_elements.binary_search_by(|prove| {
-            prove
-                .luminance()
-                .partial_cmp(&target)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+    /// Extracts the picture and typeset elements and computes their luminance
+    /// ranges without matching them, to validate settings before a conversion.
+    pub fn plan(&self) -> Result<ConversionPlan> {
+        let (_, picture_range) = self.typesetter.extract_picture_elements(
+            &self.image,
+            self.config().cell_size(),
+            self.columns,
+            self.lines,
+        )?;
+        let (typeset_elements, typeset_range) = self
+            .typesetter
+            .render_typeset(self.typesetter.characters())?;
+
+        Ok(ConversionPlan {
+            width: self.image.width(),
+            height: self.image.height(),
+            columns: self.columns,
+            lines: self.lines,
+            typeset_elements: typeset_elements.len(),
+            picture_range: picture_range.bounds(),
+            typeset_range: typeset_range.bounds(),
+        })
+    }
+
+    /// Renders a half-size thumbnail of the source with shade-block characters.
+    /// Each character covers one tile horizontally and two tiles vertically,
+    /// so the half-width blocks keep the aspect ratio of the typist-art.
+    /// The thumbnail keeps the source's tones; `invert_luminance` and `pivot`
+    /// only affect matching.
+    pub fn shade_preview(&self) -> Result<Vec<String>> {
+        let (mut picture_elements, range) = self.typesetter.extract_picture_elements(
+            &self.image,
+            self.config().cell_size(),
+            self.columns,
+            self.lines,
+        )?;
+        self.typesetter
+            .normalize_picture_elements(&mut picture_elements, range, self.columns)?;
+        let columns = self.columns as usize;
+
+        let rows: Vec<&[Element]> = picture_elements.chunks(columns).collect();
+        let preview = rows
+            .chunks(2)
+            .map(|pair| {
+                (0..columns)
+                    .map(|x| {
+                        let luminance = pair.iter().map(|row| row[x].luminance()).sum::<f64>()
+                            / pair.len() as f64;
+                        Self::ramp_character(luminance, &SHADE_RAMP)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(preview)
+    }
+
+    /// Composites the matched glyphs of `result` over the source image. Each glyph is
+    /// drawn at its tile in the average color of that tile, with its coverage as opacity.
+    /// Characters outside the typeset (such as the fill character) leave the tile as is.
+    /// With [`Config::proportional`], glyphs are spaced by their advance widths instead.
+    /// `result` must come from [`Model::convert`] on this model, without a margin.
+    pub fn overlay(&self, result: &ConversionResult) -> Result<DynamicImage> {
+        if result.columns() != self.columns || result.rows() != self.lines {
+            return Err(TypistError::InputSize {
+                actual: (result.columns() * result.rows()) as usize,
+                expected: (self.columns * self.lines) as usize,
+            });
+        }
 
-        match result {
-            Ok(i) => i,
-            Err(i) => {
-                if i == 0 {
-                    0
-                } else if i >= typeset_elements.len() {
-                    typeset_elements.len() - 1
+        let (glyphs, _) = self
+            .typesetter
+            .render_typeset(self.typesetter.characters())?;
+        let glyphs: HashMap<char, &Element> = glyphs
+            .iter()
+            .filter_map(|e| e.character().map(|c| (c, e)))
+            .collect();
+
+        let colors = self.tile_colors();
+        let cell = self.config().cell_size();
+        let scaled = self
+            .font()
+            .as_scaled(PxScale::from(self.config().glyph_scale));
+        let mut canvas = self.image.to_rgba8();
+        for (y, line) in result.lines().iter().enumerate() {
+            let mut pen = 0.0;
+            for (x, c) in line.chars().enumerate() {
+                let left = if self.config().proportional {
+                    // center the glyph's cell on its advance, as the glyph is centered in the cell.
+                    let advance = scaled.h_advance(self.font().glyph_id(c));
+                    let left = (pen + (advance - cell as f32) / 2.0).round() as i64;
+                    pen += advance;
+                    left
                 } else {
-                    let diff1 = (typeset_elements[i - 1].luminance() - target).abs();
-                    let diff2 = (typeset_elements[i].luminance() - target).abs();
-                    if diff1 < diff2 { i - 1 } else { i }
+                    (x as u32 * cell) as i64
+                };
+                let Some(glyph) = glyphs.get(&c) else {
+                    continue;
+                };
+                let top = y as u32 * cell;
+                let color = colors[y * self.columns as usize + x].map(f64::from);
+
+                for (i, coverage) in glyph.characteristics().iter().enumerate() {
+                    let (dx, dy) = (i as u32 % cell, i as u32 / cell);
+                    let Ok(px) = u32::try_from(left + dx as i64) else {
+                        continue;
+                    };
+                    if px >= canvas.width() {
+                        continue;
+                    }
+                    let pixel = canvas.get_pixel_mut(px, top + dy);
+                    // NOTE: characteristics are 1.0 for paper and 0.0 for full ink.
+                    let ink = 1.0 - coverage;
+                    for (channel, tone) in pixel.0.iter_mut().zip(color) {
+                        *channel = (*channel as f64 * coverage + tone * ink).round() as u8;
+                    }
                 }
             }
         }
+
+        Ok(DynamicImage::ImageRgba8(canvas))
     }
 
-    /// Selects the best-matching element from the given candidates
-    /// based on pixel-wise correlation similarity.
-    fn best_match_element<'a>(target: &Element, candidates: &'a [Element]) -> Option<&'a Element> {
-        let mut max = -1.0;
-        let mut best: Option<&Element> = None;
-        for candidate in candidates {
-            if let Some(result) = correlation(target.characteristics(), candidate.characteristics())
-            {
-                if result > max {
-                    max = result;
-                    best = Some(candidate);
+    /// Returns the average RGB color of every tile of the image in row-major order.
+    pub fn tile_colors(&self) -> Vec<[u8; 3]> {
+        let cell = self.config().cell_size();
+        let image = self.image.to_rgb8();
+        let mut colors = Vec::with_capacity((self.columns * self.lines) as usize);
+        for y in 0..self.lines {
+            for x in 0..self.columns {
+                let tile = imageops::crop_imm(&image, x * cell, y * cell, cell, cell);
+                let mut sum = [0u64; 3];
+                let mut count = 0;
+                for (_, _, p) in tile.pixels() {
+                    for (total, channel) in sum.iter_mut().zip(p.0) {
+                        *total += channel as u64;
+                    }
+                    count += 1;
                 }
+                colors.push(sum.map(|total| (total as f64 / count.max(1) as f64).round() as u8));
             }
         }
 
-        best
+        colors
     }
 
-    /// Finds the best-matching character element for a picture element
-    /// by combining luminance-based preselection and pixel correlation.
-    fn search_typeset_element<'a>(
-        picture_element: &'a Element,
-        typeset_elements: &'a [Element],
-    ) -> Option<&'a Element> {
-        if typeset_elements.is_empty() {
-            return None;
-        }
+    /// Renders the source as Braille patterns instead of matching glyphs.
+    /// Every tile becomes two half-width Braille characters of 2x4 dots, so each
+    /// dot covers a quarter of a tile in both directions. Dots darker than the
+    /// midpoint of the source's luminance range (or 0.5 with a fixed range) are raised.
+    /// Braille art carries no match scores.
+    pub fn braille(&self) -> Result<ConversionResult> {
+        let width = self.columns * 4;
+        let height = self.lines * 4;
+        let dots = self
+            .image
+            .resize_exact(width, height, imageops::FilterType::Triangle)
+            .to_rgba8();
+        let luminances: Vec<f64> = dots
+            .pixels()
+            .map(|p| self.config().luma.of(&p.0, self.config().channel_weights))
+            .collect();
 
-        // STEP 1: find the index of the character with the most similar average luminance.
-        let index = Self::closest_luminance_index(picture_element.luminance(), typeset_elements);
+        let (min, max) = luminances
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), &l| {
+                (min.min(l), max.max(l))
+            });
+        let threshold = if self.config().fixed_range || max - min < F64_ALMOST_ZERO {
+            0.5
+        } else {
+            (min + max) / 2.0
+        };
 
-        // STEP 2: create a slice of candidates around that index for a more detailed search.
-        // NOTE: use saturating_sub to avoid underflow when index is less than NUM_OF_CANDIDATES / 2.
-        let from = index.saturating_sub(NUM_OF_CANDIDATES / 2);
-        let to = std::cmp::min(typeset_elements.len(), from + NUM_OF_CANDIDATES);
-        let candidates = &typeset_elements[from..to];
+        let columns = width / 2;
+        let rows = height / 4;
+        let lines = (0..rows)
+            .map(|row| {
+                (0..columns)
+                    .map(|column| {
+                        let mut bits = 0;
+                        for (dy, row_bits) in BRAILLE_DOT_BITS.iter().enumerate() {
+                            for (dx, bit) in row_bits.iter().enumerate() {
+                                let x = (column * 2) as usize + dx;
+                                let y = (row * 4) as usize + dy;
+                                if luminances[y * width as usize + x] < threshold {
+                                    bits |= bit;
+                                }
+                            }
+                        }
+                        Self::braille_character(bits)
+                    })
+                    .collect()
+            })
+            .collect();
 
-        if candidates.is_empty() {
-            return Some(&typeset_elements[index]);
+        Ok(ConversionResult::new(lines, vec![], columns, rows))
+    }
+
+    /// Renders the source by mapping each tile's normalized luminance straight onto
+    /// `ramp`, ordered from blank paper to the most ink (like [`ASCII_RAMP`]),
+    /// without matching any glyphs. Half-width ramp characters are written twice
+    /// per tile so the art keeps the aspect ratio of full-width glyphs.
+    /// Ramp art carries no match scores.
+    ///
+    /// [`ASCII_RAMP`]: crate::ASCII_RAMP
+    pub fn ramp(&self, ramp: &[char]) -> Result<ConversionResult> {
+        if ramp.is_empty() {
+            return Err(TypistError::EmptyTypeset);
         }
+        let picture_elements = self.typesetter.picture_elements(
+            &self.image,
+            self.config().cell_size(),
+            self.columns,
+            self.lines,
+        )?;
 
-        // STEP 3: from the candidates, find the best match using pixel-by-pixel correlation.
-        Self::best_match_element(picture_element, candidates)
+        // NOTE: `ramp_character` expects dark to light, the reverse of an ink ramp.
+        let ramp: Vec<char> = ramp.iter().rev().copied().collect();
+        let lines = picture_elements
+            .chunks(self.columns as usize)
+            .map(|row| {
+                row.iter()
+                    .flat_map(|e| {
+                        let c = Self::ramp_character(e.luminance(), &ramp);
+                        std::iter::repeat_n(c, 3 - char_width(c) as usize)
+                    })
+                    .collect()
+            })
+            .collect();
+        let luminances = picture_elements.iter().map(Element::luminance).collect();
+
+        Ok(
+            ConversionResult::new(lines, vec![], self.columns, self.lines)
+                .with_luminances(luminances),
+        )
     }
 
-    /// Converts the picture elements into their best-matching character elements
-    /// to generate the final typist-art structure.
-    fn generate_typist_art(
-        picture_elements: &[Element],
-        typeset_elements: &[Element],
-    ) -> Vec<Element> {
-        let default = Element::default();
-        let typist_art_elements: Vec<Element> = picture_elements
-            .par_iter()
-            .map(|e| Self::search_typeset_element(e, typeset_elements).unwrap_or(&default))
-            .cloned()
+    /// Returns the Braille pattern character with the given dot bits raised.
+    fn braille_character(bits: u32) -> char {
+        char::from_u32(BRAILLE_BLANK + (bits & 0xFF)).unwrap_or(' ')
+    }
+
+    /// Maps a normalized luminance onto a character ramp ordered from dark to light.
+    fn ramp_character(luminance: f64, ramp: &[char]) -> char {
+        let index = (luminance.clamp(0.0, 1.0) * ramp.len() as f64) as usize;
+        ramp[index.min(ramp.len() - 1)]
+    }
+
+    /// Finds the widest gaps in the sorted luminance curve of the typeset.
+    /// Returns up to `count` `(lower, upper)` luminance pairs, widest first,
+    /// marking the tones where the typeset lacks coverage.
+    pub fn typeset_gaps(&self, count: usize) -> Result<Vec<(f64, f64)>> {
+        let typeset_elements = self
+            .typesetter
+            .typeset_elements(self.typesetter.characters())?;
+        Ok(Self::luminance_gaps(&typeset_elements, count))
+    }
+
+    /// Merges `extra` glyphs into the `base` typeset, but only those that fill a
+    /// tonal gap wider than `threshold` in the base's sorted luminance curve.
+    /// Returns the base characters followed by the accepted extras, in their order.
+    ///
+    /// Luminances are compared raw, before normalization. Extras are taken
+    /// greedily, so an accepted glyph narrows the gaps seen by later ones.
+    /// Extras already in the base and glyphs the font cannot render are skipped.
+    pub fn merge_typeset(
+        &self,
+        base: &[char],
+        extra: &[char],
+        threshold: f64,
+    ) -> Result<Vec<char>> {
+        let (base_elements, _) = self.typesetter.render_typeset(base)?;
+        let extra: Vec<char> = extra
+            .iter()
+            .copied()
+            .filter(|c| !base.contains(c))
             .collect();
+        let extra_elements = match self.typesetter.render_typeset(&extra) {
+            Ok((elements, _)) => elements,
+            Err(TypistError::EmptyTypeset) => Vec::new(),
+            Err(e) => return Err(e),
+        };
 
-        typist_art_elements
+        let curve: Vec<f64> = base_elements.iter().map(|e| e.luminance()).collect();
+        let tones: Vec<f64> = extra_elements.iter().map(|e| e.luminance()).collect();
+        let mut merged = base.to_vec();
+        for i in Self::gap_fillers(curve, &tones, threshold) {
+            merged.extend(extra_elements[i].character());
+        }
+
+        Ok(merged)
+    }
+
+    /// Returns the indices of the `tones` that land in a gap of the `curve` wider
+    /// than `threshold`, adding each accepted tone to the curve before the next.
+    /// Beyond the ends of the curve, the distance to the nearest end counts as the gap.
+    fn gap_fillers(mut curve: Vec<f64>, tones: &[f64], threshold: f64) -> Vec<usize> {
+        curve.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mut accepted = Vec::new();
+        for (i, &tone) in tones.iter().enumerate() {
+            let index = curve.partition_point(|&l| l < tone);
+            let gap = match (index.checked_sub(1).map(|j| curve[j]), curve.get(index)) {
+                (Some(lower), Some(&upper)) => upper - lower,
+                (Some(lower), None) => tone - lower,
+                (None, Some(&upper)) => upper - tone,
+                (None, None) => f64::INFINITY,
+            };
+            if gap > threshold {
+                curve.insert(index, tone);
+                accepted.push(i);
+            }
+        }
+
+        accepted
+    }
+
+    /// Returns up to `count` widest gaps between neighboring luminances of
+    /// the given elements, in any order.
+    fn luminance_gaps(elements: &[Element], count: usize) -> Vec<(f64, f64)> {
+        let mut luminances: Vec<f64> = elements.iter().map(|e| e.luminance()).collect();
+        luminances.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mut gaps: Vec<(f64, f64)> = luminances.windows(2).map(|w| (w[0], w[1])).collect();
+        gaps.sort_by(|a, b| {
+            (b.1 - b.0)
+                .partial_cmp(&(a.1 - a.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        gaps.truncate(count);
+
+        gaps
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
     use super::*;
+    use crate::element::GlyphOptions;
+    use crate::metric::L1;
+
+    fn test_model(characters: &[char]) -> Model {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE * 2, IMAGE_SIZE * 2);
+        Model::new(2, &image, characters, crate::TEST_FONT_DATA).unwrap()
+    }
 
     #[test]
-    fn closest_luminance_index_empty_elements() {
-        let elements: Vec<Element> = vec![];
-        assert_eq!(Model::closest_luminance_index(0.5, &elements), 0);
+    fn length_for_budget_picks_largest_fitting_length() {
+        // a 2:1 image has half as many lines as columns.
+        assert_eq!(Model::estimate_dimensions(200, 100, 40, false), (40, 20));
+        assert_eq!(Model::length_for_budget(200, 100, 800, false), Some(40));
+        assert_eq!(Model::length_for_budget(200, 100, 799, false), Some(39));
+        assert_eq!(
+            Model::length_for_budget(200, 100, u64::MAX, false),
+            Some(MAX_LENGTH)
+        );
+        assert_eq!(Model::length_for_budget(200, 100, 100, false), None);
     }
 
     #[test]
-    fn closest_luminance_index_single_element() {
-        let elements = vec![Element::new(vec![0.0; 10], 0.5, Some('A'), None)];
-        assert_eq!(Model::closest_luminance_index(0.5, &elements), 0);
+    fn dimensions_non_divisible_height() {
+        // 5 columns of 20px: 100px wide, 130px tall, i.e. 6.5 tiles.
+        assert_eq!(
+            Model::dimensions(100, 130, 5, IMAGE_SIZE, false),
+            (100, 130, 6)
+        );
+        assert_eq!(
+            Model::dimensions(100, 130, 5, IMAGE_SIZE, true),
+            (100, 130, 7)
+        );
+        assert_eq!(
+            Model::dimensions(100, 120, 5, IMAGE_SIZE, true),
+            (100, 120, 6)
+        );
     }
 
     #[test]
-    fn closest_luminance_index_multiple_elements() {
-        let elements = vec![
-            Element::new(vec![0.0; 10], 0.1, None, None),
-            Element::new(vec![0.0; 10], 0.5, None, None),
-            Element::new(vec![0.0; 10], 0.9, None, None),
-        ];
-        assert_eq!(Model::closest_luminance_index(0.5, &elements), 1);
-        assert_eq!(Model::closest_luminance_index(0.2, &elements), 0);
-        assert_eq!(Model::closest_luminance_index(0.8, &elements), 2);
+    fn local_contrast_spreads_the_tones_of_each_region() {
+        // a dark half and a bright half, each with a ramp across its 4 tiles.
+        let size = IMAGE_SIZE;
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(size * 8, size * 2, |x, _| {
+            let column = x / size;
+            let v = if column < 4 { 0 } else { 165 } + (column % 4) as u8 * 30;
+            Rgba([v, v, v, 255])
+        }));
+        let dark_spread = |local_contrast| {
+            let config = Config {
+                local_contrast,
+                ..Default::default()
+            };
+            let model =
+                Model::with_config(8, &image, &['A', 'M'], crate::TEST_FONT_DATA, config).unwrap();
+            let tiles = model.tiles().unwrap();
+            let dark = tiles[..4].iter().map(Element::luminance);
+            dark.clone().fold(f64::MIN, f64::max) - dark.fold(f64::MAX, f64::min)
+        };
+        assert!(dark_spread(None) < 0.4);
+        assert!(dark_spread(Some(3)) > 0.45);
+    }
+
+    #[test]
+    fn rank_all_candidates_puts_the_converted_character_first() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(80, 40, |x, y| {
+            let v = ((x + y) * 255 / 120) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let characters = ['A', 'M', '.', 'W'];
+        let mut model = Model::new(4, &image, &characters, crate::TEST_FONT_DATA).unwrap();
+        let result = model.convert().unwrap();
+        let converted: Vec<char> = result.lines().concat().chars().collect();
+
+        let rankings = model.rank_all_candidates(3).unwrap();
+        assert_eq!(rankings.len(), converted.len());
+        for (ranked, expected) in rankings.iter().zip(converted) {
+            assert_eq!(ranked.len(), 3);
+            assert_eq!(ranked[0].0, expected);
+            assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1), "{ranked:?}");
+        }
+        assert!(
+            model
+                .rank_all_candidates(10)
+                .unwrap()
+                .iter()
+                .all(|ranked| ranked.len() == characters.len())
+        );
+        assert!(
+            model
+                .rank_all_candidates(0)
+                .unwrap()
+                .iter()
+                .all(Vec::is_empty)
+        );
+    }
+
+    #[test]
+    fn rank_candidates_ranks_one_tile_like_the_whole_image() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(80, 40, |x, y| {
+            let v = ((x + y) * 255 / 120) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let model = Model::new(4, &image, &['A', 'M', '.', 'W'], crate::TEST_FONT_DATA).unwrap();
+        let rankings = model.rank_all_candidates(3).unwrap();
+        for (tile, expected) in model.tiles().unwrap().iter().zip(&rankings) {
+            assert_eq!(&model.rank_candidates(tile, 3), expected);
+        }
+
+        // the ASCII space cannot be rendered, so there is nothing to rank.
+        let model = Model::new(4, &image, &[' '], crate::TEST_FONT_DATA).unwrap();
+        let tile = &model.tiles().unwrap()[0];
+        assert!(model.rank_candidates(tile, 3).is_empty());
     }
 
     #[test]
-    fn best_match_element_empty_candidates() {
-        let target = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
-        let candidates: Vec<Element> = vec![];
-        assert!(Model::best_match_element(&target, &candidates).is_none());
+    fn importance_mask_matches_unimportant_tiles_by_tone() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 80, |x, y| {
+            let v = ((x + y) * 255 / 240) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let characters = ['A', 'M', '.', 'W'];
+        // only the right half matters.
+        let mask =
+            image::GrayImage::from_fn(16, 8, |x, _| image::Luma([if x < 8 { 0 } else { 255 }]));
+        let convert = |importance_mask| {
+            let config = Config {
+                importance_mask,
+                ..Default::default()
+            };
+            let mut model =
+                Model::with_config(8, &image, &characters, crate::TEST_FONT_DATA, config).unwrap();
+            let result = model.convert().unwrap();
+            (
+                model,
+                result.lines().concat().chars().collect::<Vec<char>>(),
+            )
+        };
+        let (_, plain) = convert(None);
+        let (model, masked) = convert(Some(Arc::new(mask)));
+
+        let glyphs = model.glyphs().unwrap();
+        let tiles = model.tiles().unwrap();
+        for (i, tile) in tiles.iter().enumerate() {
+            if i % 8 < 4 {
+                let closest = glyphs
+                    .iter()
+                    .min_by(|a, b| {
+                        let distance = |e: &Element| (e.luminance() - tile.luminance()).abs();
+                        distance(a).total_cmp(&distance(b))
+                    })
+                    .unwrap();
+                assert_eq!(Some(masked[i]), closest.character(), "tile {i}");
+            } else {
+                assert_eq!(masked[i], plain[i], "tile {i}");
+            }
+        }
     }
 
     #[test]
-    fn best_match_element_valid_candidates() {
-        let target = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
-        let candidates = vec![
-            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
-            Element::new(vec![0.5; 10], 0.5, Some('C'), None),
-            Element::new(vec![0.7; 10], 0.7, Some('D'), None),
+    fn breathe_oscillates_around_the_plain_conversion() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(80, 40, |x, y| {
+            let v = ((x + y) * 255 / 120) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let characters = ['M', 'A', '.', 'W'];
+        // the default metric, which a flat shift of the tones would not move.
+        let texture = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 80, |x, y| {
+            let v = ((x as f64 / 7.0).sin() * (y as f64 / 5.0).cos() + 1.0) * 127.0;
+            Rgba([v as u8, v as u8, v as u8, 255])
+        }));
+        let mut model = Model::new(8, &texture, &characters, crate::TEST_FONT_DATA).unwrap();
+        let frames = model.breathe(0.4, 4).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], model.convert().unwrap());
+        assert_ne!(frames[1].lines(), frames[0].lines());
+        assert_ne!(frames[3].lines(), frames[0].lines());
+        assert!(model.breathe(0.4, 0).unwrap().is_empty());
+
+        // scoring only the tonally closest glyph shows the direction of the change.
+        let config = Config {
+            metric_topk: Some(1),
+            ..Default::default()
+        };
+        let model =
+            Model::with_config(8, &image, &characters, crate::TEST_FONT_DATA, config).unwrap();
+        let frames = model.breathe(0.4, 4).unwrap();
+        let mean = |result: &ConversionResult| {
+            result.luminances().iter().sum::<f64>() / result.luminances().len() as f64
+        };
+        // a quarter period in, the tiles are brightest; three quarters in, darkest.
+        assert!(mean(&frames[1]) > mean(&frames[0]));
+        assert!(mean(&frames[3]) < mean(&frames[0]));
+    }
+
+    #[test]
+    fn tile_count_matches_conversion() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(100, 130, |x, y| {
+            let v = ((x + y) * 255 / 230) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        for (retain_ratio, glyph_margin) in [(false, 1), (true, 1), (false, 4)] {
+            let config = Config {
+                retain_ratio,
+                glyph_margin,
+                ..Default::default()
+            };
+            let mut model =
+                Model::with_config(5, &image, &['M', 'A', '.'], crate::TEST_FONT_DATA, config)
+                    .unwrap();
+            let result = model.convert().unwrap();
+            assert_eq!(
+                model.tile_count(5, &image),
+                result.columns() * result.rows()
+            );
+        }
+
+        let model = Model::new(5, &image, &['A'], crate::TEST_FONT_DATA).unwrap();
+        assert_eq!(model.tile_count(5, &image), 30);
+        assert_eq!(model.tile_count(4, &DynamicImage::new_rgb8(800, 20)), 4);
+        assert_eq!(model.tile_count(0, &image), 0);
+    }
+
+    #[test]
+    fn crop_region_focuses_on_the_rectangle() {
+        // a dark left half and a light right half.
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(80, 40, |x, y| {
+            let v = if x < 40 {
+                (y * 3) as u8
+            } else {
+                255 - (y * 3) as u8
+            };
+            Rgba([v, v, v, 255])
+        }));
+        let convert = |image: &DynamicImage| {
+            Model::new(2, image, &['M', 'A', '.'], crate::TEST_FONT_DATA)
+                .unwrap()
+                .convert()
+                .unwrap()
+        };
+
+        let region = Model::crop_region(&image, [0, 0, 40, 40]).unwrap();
+        assert_eq!(region.dimensions(), (40, 40));
+        assert_eq!(region.to_rgba8(), image.crop_imm(0, 0, 40, 40).to_rgba8());
+        let (full, cropped) = (convert(&image), convert(&region));
+        assert_eq!((full.rows(), cropped.rows()), (1, 2));
+        assert_ne!(full.lines(), cropped.lines());
+    }
+
+    #[test]
+    fn crop_region_rejects_out_of_bounds() {
+        let image = DynamicImage::new_rgb8(40, 30);
+        for region in [
+            [0, 0, 0, 10],
+            [30, 0, 20, 10],
+            [0, 25, 10, 10],
+            [u32::MAX, 0, 2, 2],
+        ] {
+            assert!(matches!(
+                Model::crop_region(&image, region),
+                Err(TypistError::InvalidRegion {
+                    image_width: 40,
+                    image_height: 30,
+                    ..
+                })
+            ));
+        }
+        assert!(Model::crop_region(&image, [0, 0, 40, 30]).is_ok());
+    }
+
+    #[test]
+    fn extreme_aspect_image_gets_one_row() {
+        // 4 columns of 20px squeeze this strip to 2px, less than a tile.
+        let image = RgbaImage::from_fn(800, 20, |x, _| {
+            let v = (x * 255 / 799) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let mut model = Model::new(
+            4,
+            &DynamicImage::ImageRgba8(image),
+            &['M', 'A', '.'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        assert_eq!(model.lines, 1);
+        assert_eq!(model.image.height(), IMAGE_SIZE);
+        let result = model.convert().unwrap();
+        assert_eq!((result.columns(), result.rows()), (4, 1));
+        assert_eq!(result.lines().len(), 1);
+        assert_eq!(Model::estimate_dimensions(800, 20, 4, false), (4, 1));
+    }
+
+    #[test]
+    fn auto_length_picks_best_mean_score() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 80, |x, y| {
+            let v = ((x + y) * 255 / 240) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let characters = ['M', 'A', '.'];
+        // the mask applies while scoring each candidate length.
+        let mask =
+            image::GrayImage::from_fn(16, 8, |x, _| image::Luma([if x < 8 { 0 } else { 255 }]));
+        let configs = [
+            Config::default(),
+            Config {
+                importance_mask: Some(Arc::new(mask)),
+                ..Default::default()
+            },
         ];
-        let best = Model::best_match_element(&target, &candidates);
-        assert!(best.is_some());
-        assert_eq!(best.unwrap().characteristics(), &vec![0.5; 10]);
+        for config in configs {
+            let (length, score) =
+                Model::auto_length(&image, &[4, 8], &characters, crate::TEST_FONT_DATA, &config)
+                    .unwrap();
+            let expected = [4, 8].map(|length| {
+                Model::with_config(
+                    length,
+                    &image,
+                    &characters,
+                    crate::TEST_FONT_DATA,
+                    config.clone(),
+                )
+                .unwrap()
+                .convert()
+                .unwrap()
+                .mean_score()
+                .unwrap()
+            });
+            let best = if expected[0] >= expected[1] { 0 } else { 1 };
+            assert_eq!(length, [4, 8][best]);
+            assert_eq!(score, expected[best]);
+            assert!(matches!(
+                Model::auto_length(&image, &[], &characters, crate::TEST_FONT_DATA, &config),
+                Err(TypistError::InvalidLength(0))
+            ));
+        }
     }
 
     #[test]
-    fn search_typeset_element_empty_typeset_returns_none() {
-        let picture_element = Element::new(vec![0.0; 10], 0.5, Some('A'), None);
-        let typeset_elements: Vec<Element> = vec![];
-        assert!(Model::search_typeset_element(&picture_element, &typeset_elements).is_none());
+    fn letterbox_pads_to_whole_tiles() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 130, Rgba([0, 0, 0, 255])));
+        let boxed = Model::letterbox(&image, 5, IMAGE_SIZE, Rgba([255, 0, 0, 255])).to_rgba8();
+        // 130px at 1:1 scale is 6.5 tiles; 140px is exactly 7.
+        assert_eq!(boxed.dimensions(), (100, 140));
+        assert_eq!(boxed.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(boxed.get_pixel(0, 5), &Rgba([0, 0, 0, 255]));
+        assert_eq!(boxed.get_pixel(0, 134), &Rgba([0, 0, 0, 255]));
+        assert_eq!(boxed.get_pixel(0, 135), &Rgba([255, 0, 0, 255]));
+
+        let model = Model::new(
+            5,
+            &DynamicImage::ImageRgba8(boxed),
+            &['A'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        assert_eq!(model.lines, 7);
+        // an upscaled image lands on whole tiles too.
+        let tiny = DynamicImage::new_rgb8(7, 3);
+        let boxed = Model::letterbox(&tiny, 32, IMAGE_SIZE, Rgba([255, 255, 255, 255]));
+        let (_, height, _) = Model::dimensions(7, boxed.height(), 32, IMAGE_SIZE, false);
+        assert_eq!(height % IMAGE_SIZE, 0);
+        // an image that already fits is returned unchanged.
+        let fitting = DynamicImage::new_rgb8(100, 140);
+        assert_eq!(
+            Model::letterbox(&fitting, 5, IMAGE_SIZE, Rgba([0, 0, 0, 255])).height(),
+            140
+        );
+    }
+
+    #[test]
+    fn glyph_margin_keeps_tiles_and_glyphs_the_same_size() {
+        let image = RgbaImage::from_fn(240, 120, |x, _| {
+            let v = x as u8;
+            Rgba([v, v, v, 255])
+        });
+        let config = Config {
+            glyph_margin: 4,
+            ..Default::default()
+        };
+        let cell = config.cell_size();
+        assert_eq!(cell, 26);
+        let mut model = Model::with_config(
+            4,
+            &DynamicImage::ImageRgba8(image),
+            &['M', 'A'],
+            crate::TEST_FONT_DATA,
+            config,
+        )
+        .unwrap();
+        assert_eq!(model.image.width(), 4 * cell);
+        let size = (cell * cell) as usize;
+        assert!(
+            model
+                .tiles()
+                .unwrap()
+                .iter()
+                .all(|t| t.characteristics().len() == size)
+        );
+        assert!(
+            model
+                .glyphs()
+                .unwrap()
+                .iter()
+                .all(|g| g.characteristics().len() == size)
+        );
+        assert_eq!(model.convert().unwrap().rows(), 2);
+    }
+
+    #[test]
+    fn retain_ratio_pads_bottom_tile() {
+        let image = DynamicImage::new_rgb8(100, 130);
+        let config = Config {
+            retain_ratio: true,
+            ..Default::default()
+        };
+        let model = Model::with_config(5, &image, &['A'], crate::TEST_FONT_DATA, config).unwrap();
+        assert_eq!(model.lines, 7);
+        assert_eq!(model.image.height(), 7 * IMAGE_SIZE);
+    }
+
+    #[test]
+    fn with_font_path_missing_file_returns_err() {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE, IMAGE_SIZE);
+        let result = Model::with_font_path(1, &image, &['A'], "does/not/exist.otf");
+        assert!(matches!(result, Err(TypistError::FontFile { .. })));
+    }
+
+    #[test]
+    fn with_font_path_invalid_font_returns_err() {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE, IMAGE_SIZE);
+        let result = Model::with_font_path(1, &image, &['A'], file!());
+        assert!(matches!(result, Err(TypistError::FontParse(_))));
+    }
+
+    #[test]
+    fn plan_reports_dimensions_and_ranges() {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE * 2, IMAGE_SIZE * 3);
+        let model = Model::new(2, &image, &['A', 'M'], crate::TEST_FONT_DATA).unwrap();
+        let plan = model.plan().unwrap();
+        assert_eq!((plan.width, plan.height), (IMAGE_SIZE * 2, IMAGE_SIZE * 3));
+        assert_eq!((plan.columns, plan.lines), (2, 3));
+        assert_eq!(plan.typeset_elements, 2);
+        assert_eq!(plan.picture_range, (0.0, 0.0));
+        assert!(plan.typeset_range.0 <= plan.typeset_range.1);
     }
 
     #[test]
-    fn search_typeset_element_valid_typeset_returns_some() {
-        let picture_element = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
-        let typeset_elements = vec![
-            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
-            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
-            Element::new(vec![0.5; 10], 0.5, Some('C'), None),
-            Element::new(vec![0.7; 10], 0.7, Some('D'), None),
+    fn two_stage_resize_matches_target_size() {
+        // stripes one pixel wide alias badly when resized straight to a tile.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 16, IMAGE_SIZE * 16, |x, _| {
+            let v = if x % 2 == 0 { 0 } else { 255 };
+            Rgba([v, v, v, 255])
+        });
+        let config = Config {
+            two_stage_resize: true,
+            ..Default::default()
+        };
+        let (img, lines) =
+            Model::prepare_image(&DynamicImage::ImageRgba8(image), 1, &config).unwrap();
+        assert_eq!(
+            (img.width(), img.height(), lines),
+            (IMAGE_SIZE, IMAGE_SIZE, 1)
+        );
+        // the stripes average out to mid-gray.
+        assert!(img.to_rgba8().pixels().all(|p| p[0].abs_diff(128) <= 2));
+    }
+
+    #[test]
+    fn nearest_tile_filter_keeps_hard_edges() {
+        // a checkerboard of 3x3 pixel squares, downsampled to one tile.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 3, IMAGE_SIZE * 3, |x, y| {
+            let v = if (x / 3 + y / 3) % 2 == 0 { 0 } else { 255 };
+            Rgba([v, v, v, 255])
+        });
+        let config = Config {
+            tile_filter: TileFilter::Nearest,
+            fixed_range: true,
+            ..Default::default()
+        };
+        let model = Model::with_config(
+            1,
+            &DynamicImage::ImageRgba8(image),
+            &['A'],
+            crate::TEST_FONT_DATA,
+            config,
+        )
+        .unwrap();
+        let tiles = model.tiles().unwrap();
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(
+            tiles[0].characteristics().len(),
+            (IMAGE_SIZE * IMAGE_SIZE) as usize
+        );
+        assert!(
+            tiles[0]
+                .characteristics()
+                .iter()
+                .all(|&c| c == 0.0 || c == 1.0)
+        );
+    }
+
+    #[test]
+    fn tiles_and_glyphs_match_the_matcher_input() {
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 2, IMAGE_SIZE * 2, |x, _| {
+            let v = if x < IMAGE_SIZE { 0 } else { 255 };
+            Rgba([v, v, v, 255])
+        });
+        let model = Model::new(
+            2,
+            &DynamicImage::ImageRgba8(image),
+            &['M', 'A', '.'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        let tiles = model.tiles().unwrap();
+        assert_eq!(tiles.len(), 4);
+        assert!(tiles.iter().all(|t| t.characteristics().len() == 400));
+        let glyphs = model.glyphs().unwrap();
+        assert_eq!(glyphs.len(), 3);
+        assert!(
+            glyphs
+                .windows(2)
+                .all(|w| w[0].luminance() <= w[1].luminance())
+        );
+        assert_eq!(glyphs.last().unwrap().character(), Some('.'));
+    }
+
+    #[test]
+    fn invert_luminance_remaps_the_tiles_but_not_the_shade_preview() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(
+            IMAGE_SIZE * 2,
+            IMAGE_SIZE * 2,
+            |x, _| {
+                let v = if x < IMAGE_SIZE { 0 } else { 255 };
+                Rgba([v, v, v, 255])
+            },
+        ));
+        let model = |config| {
+            Model::with_config(2, &image, &['M', 'A'], crate::TEST_FONT_DATA, config).unwrap()
+        };
+        let plain = model(Config::default());
+        let inverted = model(Config {
+            invert_luminance: true,
+            ..Config::default()
+        });
+
+        assert_eq!(
+            inverted.shade_preview().unwrap(),
+            plain.shade_preview().unwrap()
+        );
+        for (inverted, plain) in inverted.tiles().unwrap().iter().zip(plain.tiles().unwrap()) {
+            assert!((inverted.luminance() - (1.0 - plain.luminance())).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn enhance_edges_increases_contrast_at_edges() {
+        // a dark left half next to a light right half.
+        let image = RgbaImage::from_fn(4, 1, |x, _| {
+            let v = if x < 2 { 64 } else { 192 };
+            Rgba([v, v, v, 255])
+        });
+        let enhanced = Model::enhance_edges(&DynamicImage::ImageRgba8(image), 0.25).to_rgba8();
+        let values: Vec<u8> = enhanced.pixels().map(|p| p[0]).collect();
+        assert_eq!(values, vec![64, 32, 224, 192]);
+        assert_eq!(enhanced.get_pixel(0, 0)[3], 255);
+    }
+
+    #[test]
+    fn font_renders_typeset_glyphs() {
+        let model = test_model(&['A']);
+        let element = Element::from_char(model.font(), 'A', &GlyphOptions::default()).unwrap();
+        assert!(element.luminance() < 1.0);
+    }
+
+    #[test]
+    fn overlay_draws_glyphs_in_tile_color() {
+        // the left half of every tile is black, the right half white.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 2, IMAGE_SIZE * 2, |x, _| {
+            let v = if x % IMAGE_SIZE < IMAGE_SIZE / 2 {
+                0
+            } else {
+                255
+            };
+            Rgba([v, v, v, 255])
+        });
+        let model = Model::new(
+            2,
+            &DynamicImage::ImageRgba8(image),
+            &['M'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        let result = ConversionResult::new(vec!["MM".to_string(), "M　".to_string()], vec![], 2, 2);
+        let overlay = model.overlay(&result).unwrap().to_rgba8();
+        let source = model.image.to_rgba8();
+        assert_eq!(overlay.dimensions(), source.dimensions());
+        // ink over the white half takes the gray average of the tile.
+        assert!(overlay.pixels().any(|p| p[0] > 0 && p[0] < 255));
+        // the fill character leaves its tile untouched.
+        let (x, y) = (IMAGE_SIZE, IMAGE_SIZE);
+        let untouched = imageops::crop_imm(&overlay, x, y, IMAGE_SIZE, IMAGE_SIZE).to_image();
+        let original = imageops::crop_imm(&source, x, y, IMAGE_SIZE, IMAGE_SIZE).to_image();
+        assert_eq!(untouched, original);
+    }
+
+    #[test]
+    fn overlay_proportional_packs_narrow_glyphs() {
+        // one-pixel stripes differ from the tile average everywhere, so all ink shows.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 4, IMAGE_SIZE, |x, _| {
+            let v = if x % 2 == 0 { 0 } else { 255 };
+            Rgba([v, v, v, 255])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let result = ConversionResult::new(vec!["....".to_string()], vec![], 4, 1);
+        let rightmost_ink = |proportional| {
+            let config = Config {
+                proportional,
+                ..Default::default()
+            };
+            let model =
+                Model::with_config(4, &image, &['.'], crate::TEST_FONT_DATA, config).unwrap();
+            let source = model.image.to_rgba8();
+            let overlay = model.overlay(&result).unwrap().to_rgba8();
+            (0..overlay.width())
+                .filter(|&x| {
+                    (0..overlay.height()).any(|y| overlay.get_pixel(x, y) != source.get_pixel(x, y))
+                })
+                .max()
+                .unwrap()
+        };
+        // the period is much narrower than a tile, so its glyphs end up packed to the left.
+        assert!(rightmost_ink(false) >= IMAGE_SIZE * 3);
+        assert!(rightmost_ink(true) < IMAGE_SIZE * 2);
+    }
+
+    #[test]
+    fn match_cache_reuses_flat_tile_matches() {
+        // flat bands of four tones, one per column of tiles.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 4, IMAGE_SIZE * 3, |x, _| {
+            let v = (x / IMAGE_SIZE * 80) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let convert = |match_cache| {
+            let config = Config {
+                match_cache,
+                tile_filter: TileFilter::Nearest,
+                metric: std::sync::Arc::new(L1),
+                ..Default::default()
+            };
+            Model::with_config(4, &image, &['M', 'A', '.'], crate::TEST_FONT_DATA, config)
+                .unwrap()
+                .convert()
+                .unwrap()
+        };
+        let (exact, cached) = (convert(false), convert(true));
+        assert_eq!(exact.lines(), cached.lines());
+        assert_eq!(exact.scores(), cached.scores());
+    }
+
+    #[test]
+    fn match_cache_is_independent_of_thread_scheduling() {
+        // nearly flat tiles whose tones share a few buckets but differ in detail.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 16, IMAGE_SIZE * 8, |x, y| {
+            let v = 120 + ((x * 7 + y * 13) % 5) as u8 + (x / IMAGE_SIZE % 2) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let config = Config {
+            match_cache: true,
+            fixed_range: true,
+            tile_filter: TileFilter::Nearest,
+            metric: Arc::new(L1),
+            ..Default::default()
+        };
+        let model = Model::with_config(
+            16,
+            &image,
+            &['M', 'A', '.', 'W'],
+            crate::TEST_FONT_DATA,
+            config,
+        )
+        .unwrap();
+        let convert = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| model.clone().convert().unwrap())
+        };
+
+        let expected = convert(1);
+        for _ in 0..4 {
+            assert_eq!(convert(4), expected);
+        }
+    }
+
+    #[test]
+    fn tile_colors_average_each_tile() {
+        // the left tile is red, the right one half black and half white.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 2, IMAGE_SIZE, |x, _| {
+            if x < IMAGE_SIZE {
+                Rgba([255, 0, 0, 255])
+            } else if x < IMAGE_SIZE * 3 / 2 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let model = Model::new(
+            2,
+            &DynamicImage::ImageRgba8(image),
+            &['M'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        assert_eq!(model.tile_colors(), vec![[255, 0, 0], [128, 128, 128]]);
+    }
+
+    #[test]
+    fn overlay_rejects_mismatched_grid() {
+        let model = test_model(&['A']);
+        let result = ConversionResult::new(vec!["AAA".to_string()], vec![], 3, 1);
+        assert!(matches!(
+            model.overlay(&result),
+            Err(TypistError::InputSize {
+                actual: 3,
+                expected: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn new_zero_length_returns_err() {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE, IMAGE_SIZE);
+        let result = Model::new(0, &image, &['A'], crate::TEST_FONT_DATA);
+        assert!(matches!(result, Err(TypistError::InvalidLength(0))));
+    }
+
+    #[test]
+    fn solid_grey_image_converts_at_a_flat_tone() {
+        let image =
+            RgbaImage::from_pixel(IMAGE_SIZE * 3, IMAGE_SIZE * 2, Rgba([128, 128, 128, 255]));
+        let image = DynamicImage::ImageRgba8(image);
+        for local_contrast in [None, Some(3)] {
+            let config = Config {
+                local_contrast,
+                ..Default::default()
+            };
+            let mut model = Model::with_config(
+                3,
+                &image,
+                &['A', 'M', '.', 'W'],
+                crate::TEST_FONT_DATA,
+                config,
+            )
+            .unwrap();
+            assert!(model.tiles().unwrap().iter().all(|e| e.luminance() == 0.5));
+
+            let result = model.convert().unwrap();
+            let characters: Vec<char> = result.lines().concat().chars().collect();
+            assert_eq!(characters.len(), 6);
+            assert_ne!(characters[0], '　');
+            assert!(characters.iter().all(|&c| c == characters[0]));
+        }
+    }
+
+    #[test]
+    fn new_empty_image_returns_err() {
+        let image = DynamicImage::new_rgb8(0, 0);
+        let result = Model::new(1, &image, &['A'], crate::TEST_FONT_DATA);
+        assert!(matches!(result, Err(TypistError::EmptyImage)));
+    }
+
+    #[test]
+    fn new_empty_typeset_returns_err() {
+        let image = DynamicImage::new_rgb8(IMAGE_SIZE, IMAGE_SIZE);
+        let result = Model::new(1, &image, &[], crate::TEST_FONT_DATA);
+        assert!(matches!(result, Err(TypistError::EmptyTypeset)));
+    }
+
+    #[test]
+    fn braille_character_maps_dot_bits() {
+        assert_eq!(Model::braille_character(0), '\u{2800}');
+        assert_eq!(Model::braille_character(0x01 | 0x08), '⠉');
+        assert_eq!(Model::braille_character(0xFF), '⣿');
+    }
+
+    #[test]
+    fn braille_thresholds_dark_dots() {
+        // left tile black, right tile white.
+        let mut image = RgbaImage::from_pixel(IMAGE_SIZE * 2, IMAGE_SIZE, Rgba([255; 4]));
+        imageops::replace(
+            &mut image,
+            &RgbaImage::from_pixel(IMAGE_SIZE, IMAGE_SIZE, Rgba([0, 0, 0, 255])),
+            0,
+            0,
+        );
+        let model = Model::new(
+            2,
+            &DynamicImage::ImageRgba8(image),
+            &['A'],
+            crate::TEST_FONT_DATA,
+        )
+        .unwrap();
+        let result = model.braille().unwrap();
+        assert_eq!(result.lines(), ["⣿⣿\u{2800}\u{2800}"]);
+        assert_eq!((result.columns(), result.rows()), (4, 1));
+    }
+
+    #[test]
+    fn luminance_gaps_widest_first() {
+        let elements = vec![
+            Element::new(vec![], 0.0, None, None),
+            Element::new(vec![], 0.1, None, None),
+            Element::new(vec![], 0.6, None, None),
+            Element::new(vec![], 1.0, None, None),
         ];
-        let result = Model::search_typeset_element(&picture_element, &typeset_elements);
-        assert!(result.is_some());
-        let best_match = result.unwrap();
-        assert_eq!(best_match.characteristics(), &vec![0.5; 10]);
-        assert_eq!(best_match.character(), Some('C'));
+        let gaps = Model::luminance_gaps(&elements, 2);
+        assert_eq!(gaps, vec![(0.1, 0.6), (0.6, 1.0)]);
+        assert!(Model::luminance_gaps(&elements[..1], 2).is_empty());
+    }
+
+    #[test]
+    fn gap_fillers_take_only_wide_gaps() {
+        let curve = vec![1.0, 0.0, 0.5, 0.625];
+        // 0.25 fills [0.0, 0.5]; 0.125 then only sees [0.0, 0.25];
+        // 0.5625 sits in a narrow gap; 0.875 fills [0.625, 1.0].
+        let tones = [0.25, 0.125, 0.5625, 0.875];
+        assert_eq!(Model::gap_fillers(curve.clone(), &tones, 0.25), vec![0, 3]);
+        assert_eq!(Model::gap_fillers(curve, &[1.5, -0.125], 0.25), vec![0]);
+        assert_eq!(Model::gap_fillers(vec![], &[0.5], 1.0), vec![0]);
+    }
+
+    #[test]
+    fn merge_typeset_keeps_base_and_skips_duplicates() {
+        let model = test_model(&['A']);
+        let merged = model
+            .merge_typeset(&['M', '.'], &['M', 'A', ' '], 0.0)
+            .unwrap();
+        assert_eq!(merged, vec!['M', '.', 'A']);
+        let merged = model.merge_typeset(&['M', '.'], &['A'], 1.0).unwrap();
+        assert_eq!(merged, vec!['M', '.']);
+    }
+
+    #[test]
+    fn ramp_maps_luminance_to_ink() {
+        // black on the left, white on the right.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 2, IMAGE_SIZE, |x, _| {
+            let v = if x < IMAGE_SIZE { 0 } else { 255 };
+            Rgba([v, v, v, 255])
+        });
+        let model = Model::with_config(
+            2,
+            &DynamicImage::ImageRgba8(image),
+            &['A'],
+            crate::TEST_FONT_DATA,
+            Config {
+                tile_filter: TileFilter::Nearest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let ascii: Vec<char> = crate::ASCII_RAMP.chars().collect();
+        let result = model.ramp(&ascii).unwrap();
+        assert_eq!(result.lines(), ["@@  "]);
+        assert_eq!((result.columns(), result.rows()), (2, 1));
+        assert_eq!(result.luminances(), [0.0, 1.0]);
+        // full-width characters fill a tile on their own.
+        assert_eq!(model.ramp(&['　', '＃']).unwrap().lines(), ["＃　"]);
+        assert!(matches!(model.ramp(&[]), Err(TypistError::EmptyTypeset)));
+    }
+
+    #[test]
+    fn ramp_character_dark_to_light() {
+        assert_eq!(Model::ramp_character(0.0, &SHADE_RAMP), '█');
+        assert_eq!(Model::ramp_character(0.5, &SHADE_RAMP), '▒');
+        assert_eq!(Model::ramp_character(1.0, &SHADE_RAMP), ' ');
+    }
+
+    #[test]
+    fn denied_glyphs_never_appear_in_output() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 80, |x, _| {
+            let v = (x * 255 / 159) as u8;
+            Rgba([v, v, v, 255])
+        }));
+        let config = Config {
+            glyph_denylist: HashSet::from(['A', '.']),
+            ..Default::default()
+        };
+        let characters = ['A', 'M', '.', 'W'];
+        let mut model =
+            Model::with_config(4, &image, &characters, crate::TEST_FONT_DATA, config).unwrap();
+        let text = model.convert().unwrap().lines().concat();
+        assert!(!text.contains(['A', '.']), "{text}");
+        assert!(text.contains(['M', 'W']));
+
+        let config = Config {
+            glyph_denylist: HashSet::from(characters),
+            ..Default::default()
+        };
+        let mut model =
+            Model::with_config(4, &image, &characters, crate::TEST_FONT_DATA, config).unwrap();
+        assert!(matches!(model.convert(), Err(TypistError::EmptyTypeset)));
     }
 }
@@ -0,0 +1,254 @@
+use std::path::Path;
+
+use crate::config::Config;
+use crate::conversion::ConversionResult;
+use crate::converter::Converter;
+use crate::error::{Result, TypistError};
+
+/// The magic string every `.npy` file starts with.
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// A 2D array of luminance values loaded from a `.npy` file, in row-major order.
+///
+/// `uint8` arrays are scaled from 0–255 to [0, 1]; `float` arrays are taken as is
+/// and are expected to hold luminance in [0, 1] (0.0 = black).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuminanceArray {
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+}
+
+impl LuminanceArray {
+    /// Reads and parses a `.npy` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Parses the contents of a `.npy` file holding a 2D, C-order array of
+    /// `uint8`, `float32`, or `float64` values in either byte order.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let invalid = |message: &str| TypistError::Npy(message.to_string());
+
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("missing the NUMPY magic string"))?;
+        // version 1.0 has a 2-byte header length; 2.0 and 3.0 have 4 bytes.
+        let (header_len, rest) = match rest {
+            [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+            [2 | 3, _, a, b, c, d, rest @ ..] => {
+                (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest)
+            }
+            _ => return Err(invalid("unsupported format version")),
+        };
+        if rest.len() < header_len {
+            return Err(invalid("truncated header"));
+        }
+        let (header, data) = rest.split_at(header_len);
+        let header = std::str::from_utf8(header).map_err(|_| invalid("header is not text"))?;
+
+        let descr = header_value(header, "descr")
+            .and_then(|v| v.strip_prefix('\'')?.split('\'').next())
+            .ok_or_else(|| invalid("missing 'descr'"))?;
+        if header_value(header, "fortran_order").is_some_and(|v| v.starts_with("True")) {
+            return Err(invalid("Fortran-order arrays are not supported"));
+        }
+        let shape: Vec<usize> = header_value(header, "shape")
+            .and_then(|v| v.strip_prefix('(')?.split(')').next())
+            .ok_or_else(|| invalid("missing 'shape'"))?
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.parse().map_err(|_| invalid("invalid 'shape'")))
+            .collect::<Result<_>>()?;
+        let [height, width] = shape[..] else {
+            return Err(invalid("the array must be 2D"));
+        };
+        if width == 0 || height == 0 {
+            return Err(TypistError::EmptyImage);
+        }
+
+        let count = width * height;
+        let values: Vec<f64> = match descr {
+            "|u1" | "<u1" | ">u1" => data.iter().take(count).map(|&v| v as f64 / 255.0).collect(),
+            "<f4" | ">f4" => data
+                .chunks_exact(4)
+                .take(count)
+                .map(|c| {
+                    let bytes = [c[0], c[1], c[2], c[3]];
+                    if descr.starts_with('<') {
+                        f32::from_le_bytes(bytes) as f64
+                    } else {
+                        f32::from_be_bytes(bytes) as f64
+                    }
+                })
+                .collect(),
+            "<f8" | ">f8" => data
+                .chunks_exact(8)
+                .take(count)
+                .map(|c| {
+                    let bytes = [c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]];
+                    if descr.starts_with('<') {
+                        f64::from_le_bytes(bytes)
+                    } else {
+                        f64::from_be_bytes(bytes)
+                    }
+                })
+                .collect(),
+            other => {
+                return Err(TypistError::Npy(format!(
+                    "unsupported dtype {other:?}; expected uint8, float32, or float64"
+                )));
+            }
+        };
+        if values.len() < count {
+            return Err(TypistError::InputSize {
+                actual: values.len(),
+                expected: count,
+            });
+        }
+
+        Ok(LuminanceArray {
+            width,
+            height,
+            values,
+        })
+    }
+
+    /// Returns the number of columns of the array.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows of the array.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the values in row-major order.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Resamples the array (nearest neighbor) into a luminance grid for
+    /// [`Converter::convert_from_luminance_grid`] that is `columns` tiles of
+    /// `cell`×`cell` values wide, keeping the aspect ratio.
+    /// Returns the grid and its number of rows.
+    pub fn to_grid(&self, columns: u32, cell: u32) -> (Vec<f64>, u32) {
+        let rows = ((self.height * columns as usize / self.width) as u32).max(1);
+        let (grid_width, grid_height) = ((columns * cell) as usize, (rows * cell) as usize);
+
+        let mut grid = Vec::with_capacity(grid_width * grid_height);
+        for y in 0..grid_height {
+            let row = y * self.height / grid_height * self.width;
+            grid.extend((0..grid_width).map(|x| self.values[row + x * self.width / grid_width]));
+        }
+
+        (grid, rows)
+    }
+
+    /// Converts the array into typist-art `length` characters wide.
+    pub fn convert(
+        &self,
+        length: u32,
+        characters: &[char],
+        font: &[u8],
+        config: Config,
+    ) -> Result<ConversionResult> {
+        let cell = config.cell_size();
+        let converter = Converter::new(font, characters, config)?;
+        let (grid, rows) = self.to_grid(length, cell);
+        converter.convert_from_luminance_grid(&grid, length, rows)
+    }
+}
+
+/// Returns the text following `'key':` in a `.npy` header dictionary.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+    Some(header[start..].trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IMAGE_SIZE;
+
+    fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header =
+            format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+        // pad the header so the data starts on a 64-byte boundary, as NumPy does.
+        while !(MAGIC.len() + 4 + header.len() + 1).is_multiple_of(64) {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn parse_uint8_and_float_arrays() {
+        let array = LuminanceArray::parse(&npy("|u1", "(2, 3)", &[0, 51, 255, 0, 0, 0])).unwrap();
+        assert_eq!((array.width(), array.height()), (3, 2));
+        assert_eq!(&array.values()[..3], &[0.0, 0.2, 1.0]);
+
+        let data: Vec<u8> = [0.25f64, 0.75]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let array = LuminanceArray::parse(&npy("<f8", "(1, 2)", &data)).unwrap();
+        assert_eq!(array.values(), &[0.25, 0.75]);
+
+        let data: Vec<u8> = [0.5f32].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let array = LuminanceArray::parse(&npy(">f4", "(1, 1)", &data)).unwrap();
+        assert_eq!(array.values(), &[0.5]);
+    }
+
+    #[test]
+    fn parse_rejects_unsupported_arrays() {
+        let parse = |bytes: &[u8]| LuminanceArray::parse(bytes);
+        assert!(matches!(parse(b"PNG"), Err(TypistError::Npy(_))));
+        assert!(matches!(
+            parse(&npy("|u1", "(2, 2, 2)", &[0; 8])),
+            Err(TypistError::Npy(_))
+        ));
+        assert!(matches!(
+            parse(&npy("<i4", "(1, 1)", &[0; 4])),
+            Err(TypistError::Npy(_))
+        ));
+        assert!(matches!(
+            parse(&npy("|u1", "(2, 2)", &[0; 3])),
+            Err(TypistError::InputSize {
+                actual: 3,
+                expected: 4
+            })
+        ));
+        let fortran = npy("|u1", "(1, 1)", &[0]);
+        let fortran = String::from_utf8_lossy(&fortran).replace("False", "True ");
+        assert!(matches!(
+            parse(fortran.as_bytes()),
+            Err(TypistError::Npy(_))
+        ));
+    }
+
+    #[test]
+    fn to_grid_keeps_aspect_ratio() {
+        let array = LuminanceArray {
+            width: 4,
+            height: 2,
+            values: vec![0.0, 0.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.5],
+        };
+        let (grid, rows) = array.to_grid(2, IMAGE_SIZE);
+        assert_eq!(rows, 1);
+        let size = IMAGE_SIZE as usize;
+        assert_eq!(grid.len(), 2 * size * size);
+        // the top-left tile comes from the dark corner, the top-right from the light one.
+        assert_eq!(grid[0], 0.0);
+        assert_eq!(grid[2 * size - 1], 1.0);
+        assert_eq!(grid[grid.len() - 1], 0.5);
+    }
+}
@@ -0,0 +1,1571 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use ab_glyph::FontArc;
+use image::{DynamicImage, imageops};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
+};
+
+use crate::config::Config;
+use crate::conversion::ConversionResult;
+use crate::element::{Element, GlyphOptions};
+use crate::error::{Result, TypistError};
+use crate::metric::SimilarityMetric;
+use crate::model::SortOrder;
+use crate::{GLYPH_WEIGHT_BIAS, NUM_OF_CANDIDATES};
+
+/// The largest difference of any characteristic for which two rendered glyphs
+/// count as identical, so that only the first of them is kept in the typeset.
+const DUPLICATE_GLYPH_EPSILON: f64 = 1e-6;
+
+/// The number of luminance buckets the match cache quantizes tiles into.
+const MATCH_CACHE_BUCKETS: f64 = 256.0;
+
+/// The largest spread of characteristics for which a tile counts as flat, so that
+/// its match depends on its tone alone and may be cached.
+const MATCH_CACHE_MAX_SPREAD: f64 = 0.05;
+
+/// The narrowest luminance range a tile is normalized against with local contrast,
+/// as a fraction of the image's range, so that flat neighborhoods are not
+/// stretched into noise.
+const LOCAL_CONTRAST_MIN_SPAN: f64 = 0.1;
+
+/// The resampled mask value from which a tile counts as important, see
+/// [`Config::importance_mask`].
+const MASK_IMPORTANCE_THRESHOLD: f64 = 0.5;
+
+/// The running minimum and maximum of element luminances, accumulated
+/// while the elements are produced so normalization needs no extra pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LuminanceRange {
+    min: f64,
+    max: f64,
+}
+
+impl Default for LuminanceRange {
+    fn default() -> Self {
+        LuminanceRange {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl LuminanceRange {
+    /// Widens the range to include the given luminance.
+    pub(crate) fn add(&mut self, luminance: f64) {
+        self.min = self.min.min(luminance);
+        self.max = self.max.max(luminance);
+    }
+
+    /// Combines two ranges accumulated over separate parts of the elements.
+    pub(crate) fn merge(self, other: LuminanceRange) -> LuminanceRange {
+        LuminanceRange {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the range as a `(min, max)` pair.
+    pub(crate) fn bounds(&self) -> (f64, f64) {
+        (self.min, self.max)
+    }
+}
+
+/// The image-independent half of a conversion: the characters, the font that
+/// renders them, and the configuration that renders and matches them. It turns
+/// the characters into typeset elements and matches picture elements against
+/// them, but never holds a source image, so a [`Model`](crate::model::Model)
+/// and a [`Converter`](crate::converter::Converter) share it alike.
+#[derive(Debug, Clone)]
+pub(crate) struct Typesetter {
+    /// A collection of full-width characters used for rendering the art.
+    characters: Vec<char>,
+
+    /// The font used to render each character.
+    font: FontArc,
+
+    /// Tunable parameters of the conversion.
+    config: Config,
+
+    /// The rendered typeset, rendered on first use and shared by every conversion.
+    glyphs: OnceLock<Vec<Element>>,
+}
+
+impl Typesetter {
+    /// Creates a typesetter; the typeset is rendered on first use.
+    pub(crate) fn new(characters: &[char], font: FontArc, config: Config) -> Self {
+        Typesetter {
+            characters: characters.to_vec(),
+            font,
+            config,
+            glyphs: OnceLock::new(),
+        }
+    }
+
+    /// Returns the characters of the typeset.
+    pub(crate) fn characters(&self) -> &[char] {
+        &self.characters
+    }
+
+    /// Returns the font used to render the typeset.
+    pub(crate) fn font(&self) -> &FontArc {
+        &self.font
+    }
+
+    /// Returns the configuration of the conversion.
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the rendered, normalized, and ordered typeset elements exactly
+    /// as the matcher sees them, rendering them on the first call only.
+    pub(crate) fn glyphs(&self) -> Result<&[Element]> {
+        if let Some(glyphs) = self.glyphs.get() {
+            return Ok(glyphs);
+        }
+        let glyphs = self.typeset_elements(&self.characters)?;
+
+        Ok(self.glyphs.get_or_init(|| glyphs))
+    }
+
+    /// Converts `image`, already prepared to `columns`×`lines` tiles (see
+    /// [`Model::prepare_image`](crate::model::Model::prepare_image)), against
+    /// already rendered, sorted typeset elements, so several images can share
+    /// a single typesetter and typeset.
+    pub(crate) fn convert_with_typeset(
+        &self,
+        image: &DynamicImage,
+        columns: u32,
+        lines: u32,
+        typeset_elements: &[Element],
+    ) -> Result<ConversionResult> {
+        let picture_elements =
+            self.picture_elements(image, self.config.cell_size(), columns, lines)?;
+        self.assemble(&picture_elements, typeset_elements, columns, lines)
+    }
+
+    /// Converts a grid of luminance values in [0, 1] into typist-art,
+    /// bypassing image decoding. The model's own image is not used, see
+    /// [`Converter::convert_from_luminance_grid`](crate::converter::Converter::convert_from_luminance_grid).
+    pub(crate) fn convert_from_luminance_grid(
+        &self,
+        grid: &[f64],
+        columns: u32,
+        rows: u32,
+        typeset_elements: &[Element],
+    ) -> Result<ConversionResult> {
+        let size = self.config.cell_size() as usize;
+        let expected = columns as usize * rows as usize * size * size;
+        if grid.len() != expected {
+            return Err(TypistError::InputSize {
+                actual: grid.len(),
+                expected,
+            });
+        }
+
+        let stride = columns as usize * size;
+        let mut elements = Vec::with_capacity((columns * rows) as usize);
+        let mut range = LuminanceRange::default();
+        for y in 0..rows as usize {
+            for x in 0..columns as usize {
+                let mut characteristics = Vec::with_capacity(size * size);
+                for row in 0..size {
+                    let start = (y * size + row) * stride + x * size;
+                    characteristics.extend_from_slice(&grid[start..start + size]);
+                }
+                let luminance = self.config.sample.luminance(&characteristics);
+                range.add(luminance);
+                elements.push(Element::new(characteristics, luminance, None, None));
+            }
+        }
+        self.normalize_picture_elements(&mut elements, range, columns)?;
+        self.remap_for_matching(&mut elements);
+
+        self.assemble(&elements, typeset_elements, columns, rows)
+    }
+
+    /// Matches each picture element against the typeset and lays the
+    /// resulting characters out into `rows` lines of `columns` characters.
+    /// Unmatched tiles are left blank, or reported as an error in strict mode.
+    pub(crate) fn assemble(
+        &self,
+        picture_elements: &[Element],
+        typeset_elements: &[Element],
+        columns: u32,
+        rows: u32,
+    ) -> Result<ConversionResult> {
+        log::info!(
+            "Typeset elements: {}, Picture elements: {}",
+            typeset_elements.len(),
+            picture_elements.len()
+        );
+
+        let importance = self.tile_importance(columns, rows);
+        let typist_art_elements =
+            self.generate_typist_art(picture_elements, typeset_elements, importance.as_deref());
+        log::info!("Converted picture elements to typist art.");
+
+        if self.config.strict {
+            let positions: Vec<(u32, u32)> = typist_art_elements
+                .iter()
+                .enumerate()
+                .filter(|(_, (e, _))| e.is_placeholder())
+                .map(|(i, _)| (i as u32 % columns, i as u32 / columns))
+                .collect();
+            if !positions.is_empty() {
+                return Err(TypistError::UnmatchedTiles { positions });
+            }
+        }
+
+        let mut result = vec![];
+        let mut v = vec![];
+        for (i, (e, _)) in typist_art_elements.iter().enumerate() {
+            if i % columns as usize == 0 && i != 0 {
+                result.push(v.iter().collect());
+                v.clear();
+            }
+            v.push(e.character().unwrap_or(self.config.fill_char));
+        }
+        if !v.is_empty() {
+            result.push(v.iter().collect());
+        }
+        let scores = typist_art_elements.iter().map(|(_, s)| *s).collect();
+        // NOTE: unmatched tiles read as blank paper.
+        let luminances = typist_art_elements
+            .iter()
+            .map(|(e, _)| {
+                if e.is_placeholder() {
+                    1.0
+                } else {
+                    e.luminance()
+                }
+            })
+            .collect();
+
+        Ok(ConversionResult::new(result, scores, columns, rows).with_luminances(luminances))
+    }
+
+    /// Resamples the importance mask, if any, to one value in [0, 1] per tile of
+    /// a `columns`×`rows` grid, in row-major order.
+    pub(crate) fn tile_importance(&self, columns: u32, rows: u32) -> Option<Vec<f64>> {
+        let mask = self.config.importance_mask.as_deref()?;
+        let tiles = imageops::resize(mask, columns, rows, imageops::FilterType::Triangle);
+        let important = tiles
+            .pixels()
+            .filter(|p| p[0] as f64 / 255.0 >= MASK_IMPORTANCE_THRESHOLD)
+            .count();
+        log::info!(
+            "Importance mask: {important} of {} tiles are matched in full",
+            columns * rows
+        );
+
+        Some(tiles.pixels().map(|p| p[0] as f64 / 255.0).collect())
+    }
+
+    /// Like `generate_typist_art`, but keeps the `n` best candidates of each
+    /// picture element with their unbiased scores instead of just the best one.
+    pub(crate) fn rank_elements(
+        &self,
+        picture_elements: &[Element],
+        typeset_elements: &[Element],
+        importance: Option<&[f64]>,
+        n: usize,
+    ) -> Vec<Vec<(char, f64)>> {
+        let picture_elements = &*self.at_match_resolution(picture_elements);
+        let typeset_elements = &*self.at_match_resolution(typeset_elements);
+        let metric = self.config.metric.as_ref();
+
+        picture_elements
+            .par_iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let importance = importance.map(|v| Self::importance_at(v, i));
+                let mut ranked: Vec<(char, f64, f64)> = self
+                    .candidates(e, typeset_elements, importance)
+                    .into_iter()
+                    .filter_map(|candidate| {
+                        let character = candidate.character()?;
+                        let score =
+                            metric.score(e.characteristics(), candidate.characteristics())?;
+                        let key = Self::rank_key(score, candidate, metric.higher_is_better());
+                        Some((character, score, key))
+                    })
+                    .collect();
+                // NOTE: a stable sort keeps ties in candidate order, as the matcher does.
+                ranked.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+
+                ranked
+                    .into_iter()
+                    .take(n)
+                    .map(|(character, score, _)| (character, score))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Divides the input image into a grid of picture elements (tiles),
+    /// computes their luminance characteristics, and normalizes them.
+    pub(crate) fn picture_elements(
+        &self,
+        image: &DynamicImage,
+        size: u32,
+        columns: u32,
+        lines: u32,
+    ) -> Result<Vec<Element>> {
+        let (mut elements, range) = self.extract_picture_elements(image, size, columns, lines)?;
+
+        // normalize the luminance of the picture elements.
+        self.normalize_picture_elements(&mut elements, range, columns)?;
+        self.remap_for_matching(&mut elements);
+
+        Ok(elements)
+    }
+
+    /// Crops the image into tiles and computes their raw luminance characteristics,
+    /// along with the range of their luminance.
+    pub(crate) fn extract_picture_elements(
+        &self,
+        image: &DynamicImage,
+        size: u32,
+        columns: u32,
+        lines: u32,
+    ) -> Result<(Vec<Element>, LuminanceRange)> {
+        // NOTE: folding and reducing an indexed parallel iterator keeps the chunks
+        // in order, so the tiles stay row-major.
+        (0..columns * lines)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % columns, i / columns);
+                let block_image = image.crop_imm(x * size, y * size, size, size);
+                Element::from_image_with_luma(
+                    block_image,
+                    self.config.sample,
+                    self.config.channel_weights,
+                    self.config.luma,
+                    self.config.alpha_as_lightness,
+                )
+            })
+            .try_fold(
+                || (Vec::new(), LuminanceRange::default()),
+                |(mut elements, mut range), element| {
+                    let element = element?;
+                    range.add(element.luminance());
+                    elements.push(element);
+                    Ok((elements, range))
+                },
+            )
+            .try_reduce(
+                || (Vec::new(), LuminanceRange::default()),
+                |(mut elements, range), (rest, other)| {
+                    elements.extend(rest);
+                    Ok((elements, range.merge(other)))
+                },
+            )
+    }
+
+    /// Normalizes picture elements by their own luminance range,
+    /// unless the configuration asks for a fixed range.
+    pub(crate) fn normalize_picture_elements(
+        &self,
+        elements: &mut [Element],
+        range: LuminanceRange,
+        columns: u32,
+    ) -> Result<()> {
+        // NOTE: raw luminance is already within [0, 1], so skipping is the fixed-range normalization.
+        if let Some(window) = self.config.local_contrast {
+            Self::normalize_locally(elements, range, columns, window)?;
+        } else if self.config.fixed_range {
+            log::info!("Using fixed luminance range: [0, 1]");
+        } else {
+            Self::normalize_elements(elements, range)?;
+        }
+
+        Ok(())
+    }
+
+    /// Pivots and inverts normalized picture elements if requested. Only the tiles
+    /// handed to matching are remapped, so previews of the source keep its tones.
+    pub(crate) fn remap_for_matching(&self, elements: &mut [Element]) {
+        if let Some(pivot) = self.config.pivot {
+            elements.iter_mut().for_each(|e| e.pivot(pivot));
+        }
+        if self.config.invert_luminance {
+            elements.iter_mut().for_each(Element::invert);
+        }
+    }
+
+    /// Renders each character into an image using the given font, converts
+    /// them into elements, normalizes their luminance, and sorts them by brightness.
+    pub(crate) fn typeset_elements(&self, characters: &[char]) -> Result<Vec<Element>> {
+        let (elements, range) = self.render_typeset(characters)?;
+        let mut elements = Self::dedup_elements(elements);
+        if !self.config.glyph_weights.is_empty() {
+            elements = elements
+                .into_iter()
+                .map(|e| {
+                    match e
+                        .character()
+                        .and_then(|c| self.config.glyph_weights.get(&c))
+                    {
+                        Some(&weight) => e.with_weight(weight),
+                        None => e,
+                    }
+                })
+                .collect();
+        }
+
+        // normalize the luminance of the typeset elements.
+        Self::normalize_elements(&mut elements, range)?;
+
+        if self.config.collapse_case {
+            elements = Self::collapse_case(elements);
+        }
+
+        // sort the typeset elements.
+        self.sort_elements(&mut elements);
+        log::debug!("Sorted typeset elements by {:?}.", self.config.sort);
+        for e in &elements {
+            log::debug!(
+                "Character: {:?}, Luminance: {}",
+                e.character(),
+                e.luminance(),
+            );
+        }
+
+        Ok(elements)
+    }
+
+    /// Arranges the typeset elements in the configured [`SortOrder`], with an
+    /// unstable sort if the configuration allows one.
+    fn sort_elements(&self, elements: &mut [Element]) {
+        let by_key = |key: fn(&Element) -> f64| {
+            move |a: &Element, b: &Element| {
+                key(a)
+                    .partial_cmp(&key(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }
+        };
+        let key: Option<fn(&Element) -> f64> = match self.config.sort {
+            SortOrder::Luminance => Some(Element::luminance),
+            SortOrder::Ink => Some(Self::ink),
+            SortOrder::None => None,
+        };
+        match key {
+            Some(key) if self.config.unstable_sort => elements.sort_unstable_by(by_key(key)),
+            Some(key) => elements.sort_by(by_key(key)),
+            None => {}
+        }
+    }
+
+    /// Removes elements whose characteristics are identical, within
+    /// `DUPLICATE_GLYPH_EPSILON`, to those of an earlier element, such as
+    /// full-width and half-width variants that render alike at small sizes.
+    fn dedup_elements(elements: Vec<Element>) -> Vec<Element> {
+        let same = |a: &Element, b: &Element| {
+            a.characteristics().len() == b.characteristics().len()
+                && a.characteristics()
+                    .iter()
+                    .zip(b.characteristics())
+                    .all(|(x, y)| (x - y).abs() <= DUPLICATE_GLYPH_EPSILON)
+        };
+
+        // NOTE: identical glyphs have the same luminance, so only elements within
+        // the epsilon of each other in luminance order need to be compared.
+        let mut order: Vec<usize> = (0..elements.len()).collect();
+        order.sort_by(|&a, &b| {
+            elements[a]
+                .luminance()
+                .partial_cmp(&elements[b].luminance())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut dropped = vec![false; elements.len()];
+        for (position, &i) in order.iter().enumerate() {
+            if dropped[i] {
+                continue;
+            }
+            let tone = elements[i].luminance();
+            for &j in order[position + 1..]
+                .iter()
+                .take_while(|&&j| elements[j].luminance() - tone <= DUPLICATE_GLYPH_EPSILON)
+            {
+                if dropped[j] || !same(&elements[i], &elements[j]) {
+                    continue;
+                }
+                // keep whichever comes first in the typeset.
+                let (first, later) = (i.min(j), i.max(j));
+                log::debug!(
+                    "Dropping {:?}: identical to {:?}",
+                    elements[later].character(),
+                    elements[first].character()
+                );
+                dropped[later] = true;
+                if later == i {
+                    break;
+                }
+            }
+        }
+
+        let removed = dropped.iter().filter(|&&d| d).count();
+        if removed > 0 {
+            log::info!("Removed {removed} glyphs identical to earlier ones from the typeset");
+        }
+
+        elements
+            .into_iter()
+            .zip(dropped)
+            .filter_map(|(e, dropped)| (!dropped).then_some(e))
+            .collect()
+    }
+
+    /// Keeps a single glyph out of each group of case variants (such as `A`/`a`
+    /// or `Ａ`/`ａ`), so they do not crowd each other out of the candidate slots.
+    ///
+    /// On collision, the variant whose tone lies farthest from every glyph outside
+    /// the group wins, since it covers the tone the rest of the typeset serves worst.
+    /// Ties keep the variant that comes first in the typeset.
+    fn collapse_case(elements: Vec<Element>) -> Vec<Element> {
+        let key = |e: &Element| {
+            e.character()
+                .filter(|c| c.is_uppercase() || c.is_lowercase())
+                .and_then(|c| c.to_lowercase().next())
+        };
+        let mut groups: HashMap<char, Vec<usize>> = HashMap::new();
+        for (i, e) in elements.iter().enumerate() {
+            if let Some(k) = key(e) {
+                groups.entry(k).or_default().push(i);
+            }
+        }
+
+        let mut dropped = vec![false; elements.len()];
+        for (k, members) in groups.iter().filter(|(_, m)| m.len() > 1) {
+            let distance = |i: usize| {
+                let tone = elements[i].luminance();
+                elements
+                    .iter()
+                    .filter(|e| key(e) != Some(*k))
+                    .map(|e| (e.luminance() - tone).abs())
+                    .fold(f64::INFINITY, f64::min)
+            };
+            let mut winner = members[0];
+            for &i in &members[1..] {
+                if distance(i) > distance(winner) {
+                    winner = i;
+                }
+            }
+            for &i in members.iter().filter(|&&i| i != winner) {
+                log::debug!(
+                    "Collapsing {:?} into {:?}",
+                    elements[i].character(),
+                    elements[winner].character()
+                );
+                dropped[i] = true;
+            }
+        }
+
+        elements
+            .into_iter()
+            .zip(dropped)
+            .filter_map(|(e, dropped)| (!dropped).then_some(e))
+            .collect()
+    }
+
+    /// Renders each character into an element with raw, unnormalized luminance,
+    /// along with the range of their luminance.
+    /// Characters the font cannot render are skipped with a warning, and those
+    /// in [`Config::glyph_denylist`] are skipped silently.
+    pub(crate) fn render_typeset(
+        &self,
+        characters: &[char],
+    ) -> Result<(Vec<Element>, LuminanceRange)> {
+        let allowed: Vec<char> = characters
+            .iter()
+            .copied()
+            .filter(|c| !self.config.glyph_denylist.contains(c))
+            .collect();
+        if allowed.len() < characters.len() {
+            log::debug!(
+                "Removed {} denied characters from the typeset.",
+                characters.len() - allowed.len()
+            );
+        }
+        let characters = allowed;
+        let options = GlyphOptions::from(&self.config);
+        let rendered: Vec<Result<Element>> = characters
+            .par_iter()
+            .map(|c| Element::from_char(&self.font, *c, &options))
+            .collect();
+
+        let mut elements = Vec::with_capacity(rendered.len());
+        let mut range = LuminanceRange::default();
+        for result in rendered {
+            match result {
+                Ok(element) => {
+                    range.add(element.luminance());
+                    elements.push(element);
+                }
+                Err(TypistError::GlyphOutline(c)) => {
+                    log::warn!("Skipping character {c:?}: the font cannot render it");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if elements.is_empty() {
+            return Err(TypistError::EmptyTypeset);
+        }
+
+        Ok((elements, range))
+    }
+
+    /// Returns the ink density of an element: the sum of its inverted characteristics.
+    fn ink(element: &Element) -> f64 {
+        element.characteristics().iter().map(|c| 1.0 - c).sum()
+    }
+
+    /// Normalizes the luminance and pixel characteristics of each element
+    /// against their previously accumulated luminance range. If every element
+    /// shares one luminance, such as the tiles of a solid image, they all map
+    /// to the flat tone 0.5 (see [`Typesetter::normalize_element`]).
+    fn normalize_elements(elements: &mut [Element], range: LuminanceRange) -> Result<()> {
+        let (min, max) = range.bounds();
+        log::info!("Luminance range: [{min}, {max}]");
+        if min >= max {
+            log::warn!("All elements share the luminance {min}; matching them as a flat 0.5");
+        }
+
+        elements
+            .par_iter_mut()
+            .try_for_each(|e| Self::normalize_element(e, min, max))?;
+        log::info!("Normalized elements.");
+
+        Ok(())
+    }
+
+    /// Normalizes each tile of a grid `columns` tiles wide against the luminance
+    /// range of the `window`×`window` tiles around it (an even window is widened
+    /// by one to stay centered), instead of the range of the whole image.
+    /// Ranges narrower than [`LOCAL_CONTRAST_MIN_SPAN`] of the image's range are
+    /// widened around their middle, within the image's range.
+    fn normalize_locally(
+        elements: &mut [Element],
+        range: LuminanceRange,
+        columns: u32,
+        window: u32,
+    ) -> Result<()> {
+        let (global_min, global_max) = range.bounds();
+        let min_span = (global_max - global_min).max(0.0) * LOCAL_CONTRAST_MIN_SPAN;
+        let columns = columns as usize;
+        let rows = elements.len().div_ceil(columns);
+        let radius = (window / 2) as usize;
+        log::info!("Normalizing luminance locally over {window}x{window} tiles");
+
+        let luminances: Vec<f64> = elements.iter().map(Element::luminance).collect();
+        elements.par_iter_mut().enumerate().try_for_each(|(i, e)| {
+            let (x, y) = (i % columns, i / columns);
+            let mut local = LuminanceRange::default();
+            for ny in y.saturating_sub(radius)..(y + radius + 1).min(rows) {
+                for nx in x.saturating_sub(radius)..(x + radius + 1).min(columns) {
+                    if let Some(&luminance) = luminances.get(ny * columns + nx) {
+                        local.add(luminance);
+                    }
+                }
+            }
+
+            let (mut min, mut max) = local.bounds();
+            if max - min < min_span {
+                let low = ((min + max - min_span) / 2.0).clamp(global_min, global_max - min_span);
+                (min, max) = (low, low + min_span);
+            }
+            Self::normalize_element(e, min, max)
+        })
+    }
+
+    /// Normalizes an element against the range from `min` to `max`. An empty range has
+    /// no contrast to stretch, so a unit range centered on it is used instead:
+    /// the element's luminance becomes 0.5 and any texture keeps its contrast.
+    fn normalize_element(element: &mut Element, min: f64, max: f64) -> Result<()> {
+        if min >= max {
+            element.normalized(min - 0.5, min + 0.5)
+        } else {
+            element.normalized(min, max)
+        }
+    }
+
+    /// Finds the index of the element in the typeset list whose luminance is
+    /// closest to the given target luminance value.
+    fn closest_luminance_index(target: f64, typeset_elements: &[Element]) -> usize {
+        let result = typeset_elements.binary_search_by(|prove| {
+            prove
+                .luminance()
+                .partial_cmp(&target)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        match result {
+            Ok(i) => i,
+            Err(i) => {
+                if i == 0 {
+                    0
+                } else if i >= typeset_elements.len() {
+                    typeset_elements.len() - 1
+                } else {
+                    let diff1 = (typeset_elements[i - 1].luminance() - target).abs();
+                    let diff2 = (typeset_elements[i].luminance() - target).abs();
+                    if diff1 < diff2 { i - 1 } else { i }
+                }
+            }
+        }
+    }
+
+    /// Returns the key candidates are ranked by, higher being better for every
+    /// metric: the score biased by the candidate's usage weight (see
+    /// [`Element::with_weight`]).
+    fn rank_key(score: f64, candidate: &Element, higher_is_better: bool) -> f64 {
+        let direction = if higher_is_better { 1.0 } else { -1.0 };
+        direction * score + GLYPH_WEIGHT_BIAS * candidate.log_weight()
+    }
+
+    /// Selects the best-matching element from the given candidates by `metric`,
+    /// along with its score. Each candidate's score is biased by its usage weight
+    /// (see [`Element::with_weight`]) for the comparison, but returned unbiased.
+    fn best_match_element<'a>(
+        target: &Element,
+        candidates: impl IntoIterator<Item = &'a Element>,
+        metric: &dyn SimilarityMetric,
+    ) -> Option<(&'a Element, f64)> {
+        let higher_is_better = metric.higher_is_better();
+        let mut best: Option<(&Element, f64, f64)> = None;
+        for candidate in candidates {
+            let Some(score) = metric.score(target.characteristics(), candidate.characteristics())
+            else {
+                continue;
+            };
+            let key = Self::rank_key(score, candidate, higher_is_better);
+            if best.is_none_or(|(_, _, best_key)| key > best_key) {
+                best = Some((candidate, score, key));
+            }
+        }
+
+        best.map(|(element, score, _)| (element, score))
+    }
+
+    /// Returns the resampled mask value of tile `i`, counting tiles beyond the
+    /// mask as important.
+    fn importance_at(importance: &[f64], i: usize) -> f64 {
+        importance.get(i).copied().unwrap_or(1.0)
+    }
+
+    /// Picks the typeset elements a picture element is scored against, the same
+    /// way for a conversion and
+    /// [`Model::rank_candidates`](crate::model::Model::rank_candidates). `importance` is the
+    /// tile's resampled mask value if a mask is set: tiles below the threshold
+    /// only get the tonally closest element, and the others twice the default
+    /// candidates, unless [`Config::metric_topk`] sets their number.
+    fn candidates<'a>(
+        &self,
+        picture_element: &Element,
+        typeset_elements: &'a [Element],
+        importance: Option<f64>,
+    ) -> Vec<&'a Element> {
+        let target = picture_element.luminance();
+        let sorted = self.config.sort == SortOrder::Luminance;
+        let widen = if importance.is_some() { 2 } else { 1 };
+        let topk = self.config.metric_topk;
+        let count = topk.unwrap_or(NUM_OF_CANDIDATES * widen);
+
+        match self.config.luminance_band {
+            _ if importance.is_some_and(|v| v < MASK_IMPORTANCE_THRESHOLD) => {
+                Self::closest_element(target, typeset_elements, sorted)
+                    .into_iter()
+                    .collect()
+            }
+            Some(band) => Self::band_candidates(target, typeset_elements, band, sorted, topk),
+            None if sorted => Self::tonal_window(target, typeset_elements, count)
+                .iter()
+                .collect(),
+            None => Self::closest_candidates(target, typeset_elements.iter().collect(), count),
+        }
+    }
+
+    /// Returns the `count` elements of a luminance-sorted typeset around the one
+    /// whose luminance is closest to `target`, or just that one if `count` is zero.
+    fn tonal_window(target: f64, typeset_elements: &[Element], count: usize) -> &[Element] {
+        if typeset_elements.is_empty() {
+            return typeset_elements;
+        }
+
+        let index = Self::closest_luminance_index(target, typeset_elements);
+        // NOTE: use saturating_sub to avoid underflow when index is less than count / 2.
+        let from = index.saturating_sub(count / 2);
+        let to = std::cmp::min(typeset_elements.len(), from + count);
+        if from == to {
+            return &typeset_elements[index..=index];
+        }
+
+        &typeset_elements[from..to]
+    }
+
+    /// Keeps the `count` candidates whose luminance is closest to `target`, in no particular order.
+    fn closest_candidates(
+        target: f64,
+        mut candidates: Vec<&Element>,
+        count: usize,
+    ) -> Vec<&Element> {
+        let distance = |e: &Element| (e.luminance() - target).abs();
+        if candidates.len() > count {
+            candidates.select_nth_unstable_by(count, |a, b| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            candidates.truncate(count);
+        }
+
+        candidates
+    }
+
+    /// Returns the element whose luminance is closest to `target`, by binary
+    /// search if the typeset is `sorted` by luminance, or by a linear scan.
+    fn closest_element(
+        target: f64,
+        typeset_elements: &[Element],
+        sorted: bool,
+    ) -> Option<&Element> {
+        if sorted {
+            typeset_elements.get(Self::closest_luminance_index(target, typeset_elements))
+        } else {
+            Self::closest_candidates(target, typeset_elements.iter().collect(), 1)
+                .first()
+                .copied()
+        }
+    }
+
+    /// Returns every element whose luminance is within `band` of `target`,
+    /// keeping only the `topk` closest of them if given, or the tonally
+    /// closest element when the band is empty.
+    fn band_candidates(
+        target: f64,
+        typeset_elements: &[Element],
+        band: f64,
+        sorted: bool,
+        topk: Option<usize>,
+    ) -> Vec<&Element> {
+        let distance = |e: &Element| (e.luminance() - target).abs();
+        let candidates: Vec<&Element> = if sorted {
+            let from = typeset_elements.partition_point(|e| e.luminance() < target - band);
+            let to = typeset_elements.partition_point(|e| e.luminance() <= target + band);
+            typeset_elements[from..to].iter().collect()
+        } else {
+            typeset_elements
+                .iter()
+                .filter(|e| distance(e) <= band)
+                .collect()
+        };
+
+        if candidates.is_empty() {
+            return typeset_elements
+                .iter()
+                .min_by(|a, b| {
+                    distance(a)
+                        .partial_cmp(&distance(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .into_iter()
+                .collect();
+        }
+
+        match topk {
+            Some(k) => Self::closest_candidates(target, candidates, k),
+            None => candidates,
+        }
+    }
+
+    /// Returns the elements with characteristics downsampled to
+    /// [`Config::match_resolution`], or the elements themselves if unset.
+    fn at_match_resolution<'a>(&self, elements: &'a [Element]) -> Cow<'a, [Element]> {
+        let Some(n) = self.config.match_resolution else {
+            return Cow::Borrowed(elements);
+        };
+
+        Cow::Owned(
+            elements
+                .par_iter()
+                .map(|e| {
+                    Element::new(e.downsample(n), e.luminance(), e.character(), None)
+                        .with_weight(e.weight())
+                })
+                .collect(),
+        )
+    }
+
+    /// Converts the picture elements into their best-matching character elements
+    /// to generate the final typist-art structure. Unmatched tiles become the
+    /// default element without a score.
+    fn generate_typist_art(
+        &self,
+        picture_elements: &[Element],
+        typeset_elements: &[Element],
+        importance: Option<&[f64]>,
+    ) -> Vec<(Element, Option<f64>)> {
+        // NOTE: the downsampled typeset is built once and shared by every tile.
+        let picture_elements = &*self.at_match_resolution(picture_elements);
+        let typeset_elements = &*self.at_match_resolution(typeset_elements);
+
+        let default = Element::default();
+        let metric = self.config.metric.as_ref();
+        let tile_importance = |i: usize| importance.map(|v| Self::importance_at(v, i));
+        let important =
+            |i: usize| tile_importance(i).is_none_or(|v| v >= MASK_IMPORTANCE_THRESHOLD);
+        let search = |i: usize, e: &Element| {
+            let candidates = self.candidates(e, typeset_elements, tile_importance(i));
+            match Self::best_match_element(e, candidates, metric) {
+                Some((element, score)) => (element.clone(), Some(score)),
+                None => (default.clone(), None),
+            }
+        };
+
+        if !self.config.match_cache {
+            return picture_elements
+                .par_iter()
+                .enumerate()
+                .map(|(i, e)| search(i, e))
+                .collect();
+        }
+
+        // NOTE: only flat tiles are cached; the match of a tile with structure
+        // depends on its full characteristics, not just its tone. The first tile of
+        // each bucket is matched on behalf of the others, so the cached glyph does
+        // not depend on the order in which the threads finish.
+        let keys: Vec<Option<(u32, bool)>> = picture_elements
+            .par_iter()
+            .enumerate()
+            .map(|(i, e)| Self::match_cache_key(e).map(|key| (key, important(i))))
+            .collect();
+        let mut representatives: HashMap<(u32, bool), usize> = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            if let Some(key) = key {
+                representatives.entry(*key).or_insert(i);
+            }
+        }
+        let cache: HashMap<(u32, bool), Element> = representatives
+            .par_iter()
+            .map(|(&key, &i)| (key, search(i, &picture_elements[i]).0))
+            .collect();
+
+        let typist_art_elements = picture_elements
+            .par_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (e, key))| {
+                let Some(element) = key.and_then(|key| cache.get(&key)) else {
+                    return search(i, e);
+                };
+                let score = match element.character() {
+                    Some(_) => metric.score(e.characteristics(), element.characteristics()),
+                    None => None,
+                };
+                (element.clone(), score)
+            })
+            .collect();
+
+        let flat = keys.iter().flatten().count();
+        let hits = flat - representatives.len();
+        log::info!(
+            "Match cache: {hits} hits out of {flat} flat tiles ({:.1}% of {} tiles)",
+            hits as f64 / picture_elements.len().max(1) as f64 * 100.0,
+            picture_elements.len()
+        );
+
+        typist_art_elements
+    }
+
+    /// Returns the match cache bucket of a flat picture element's luminance,
+    /// or `None` if its characteristics vary too much to be matched by tone alone.
+    fn match_cache_key(element: &Element) -> Option<u32> {
+        let (min, max) = element
+            .characteristics()
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        if element.characteristics().is_empty() || max - min > MATCH_CACHE_MAX_SPREAD {
+            return None;
+        }
+
+        Some((element.luminance().clamp(0.0, 1.0) * (MATCH_CACHE_BUCKETS - 1.0)).round() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::IMAGE_SIZE;
+    use crate::metric::{L1, Pearson};
+
+    fn test_typesetter(characters: &[char], config: Config) -> Typesetter {
+        let font = FontArc::try_from_slice(crate::TEST_FONT_DATA).unwrap();
+        Typesetter::new(characters, font, config)
+    }
+
+    #[test]
+    fn local_contrast_keeps_flat_regions_flat() {
+        let elements = |values: &[f64]| -> Vec<Element> {
+            values
+                .iter()
+                .map(|&v| Element::new(vec![v], v, None, None))
+                .collect()
+        };
+        // the tiles on the left differ by far less than the minimum span.
+        let mut tiles = elements(&[0.1, 0.101, 0.9, 0.9]);
+        let mut range = LuminanceRange::default();
+        tiles.iter().for_each(|e| range.add(e.luminance()));
+        Typesetter::normalize_locally(&mut tiles, range, 4, 2).unwrap();
+        assert!((tiles[1].luminance() - tiles[0].luminance()) < 0.01);
+        assert!((tiles[3].luminance() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rank_elements_agree_with_the_matcher_beyond_the_default_window() {
+        // more glyphs than NUM_OF_CANDIDATES, with distinct patterns and weights.
+        let glyphs: Vec<Element> = (0..40u32)
+            .map(|i| {
+                let pattern = (0..16u32)
+                    .map(|j| ((i * 7 + j * j * 3 + i * j) % 11) as f64 / 10.0)
+                    .collect();
+                let character = char::from_u32(0x4e00 + i);
+                Element::new(pattern, i as f64 / 39.0, character, None)
+                    .with_weight(1.0 + (i % 3) as f64)
+            })
+            .collect();
+        let tiles: Vec<Element> = (0..30u32)
+            .map(|i| {
+                let pattern = (0..16u32)
+                    .map(|j| ((i * 5 + j * 3 + i * j * j) % 13) as f64 / 12.0)
+                    .collect();
+                Element::new(pattern, (i * 17 % 30) as f64 / 29.0, None, None)
+            })
+            .collect();
+        let importance: Vec<f64> = (0..30)
+            .map(|i| if i % 4 == 0 { 0.0 } else { 1.0 })
+            .collect();
+
+        let configs = [
+            Config::default(),
+            Config {
+                luminance_band: Some(0.2),
+                ..Default::default()
+            },
+            Config {
+                metric_topk: Some(5),
+                sort: SortOrder::Ink,
+                ..Default::default()
+            },
+            Config {
+                match_resolution: Some(2),
+                metric: Arc::new(L1),
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            let typesetter = test_typesetter(&['A', 'M'], config);
+            for importance in [None, Some(importance.as_slice())] {
+                let matched = typesetter.generate_typist_art(&tiles, &glyphs, importance);
+                let ranked = typesetter.rank_elements(&tiles, &glyphs, importance, 3);
+                for ((element, score), ranked) in matched.iter().zip(&ranked) {
+                    assert_eq!(Some(ranked[0].0), element.character());
+                    assert_eq!(Some(ranked[0].1), *score);
+                }
+                // unimportant tiles only get the tonally closest glyph.
+                let sizes: Vec<usize> = ranked.iter().map(Vec::len).collect();
+                let single = sizes.iter().step_by(4).all(|&len| len == 1);
+                assert_eq!(single, importance.is_some(), "{sizes:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn importance_mask_widens_only_the_default_candidate_count() {
+        let glyphs: Vec<Element> = (0..80u32)
+            .map(|i| {
+                let luminance = i as f64 / 79.0;
+                Element::new(
+                    vec![luminance; 4],
+                    luminance,
+                    char::from_u32(0x4e00 + i),
+                    None,
+                )
+            })
+            .collect();
+        let tiles = [Element::new(vec![0.5; 4], 0.5, None, None)];
+        let mut config = Config {
+            metric: Arc::new(L1),
+            ..Default::default()
+        };
+
+        let candidates = |config: &Config, importance: Option<&[f64]>| {
+            test_typesetter(&['A', 'M'], config.clone()).rank_elements(
+                &tiles,
+                &glyphs,
+                importance,
+                usize::MAX,
+            )[0]
+            .len()
+        };
+        assert_eq!(candidates(&config, None), NUM_OF_CANDIDATES);
+        assert_eq!(candidates(&config, Some(&[1.0])), NUM_OF_CANDIDATES * 2);
+        config.metric_topk = Some(5);
+        assert_eq!(candidates(&config, None), 5);
+        assert_eq!(candidates(&config, Some(&[1.0])), 5);
+        assert_eq!(candidates(&config, Some(&[0.0])), 1);
+    }
+
+    #[test]
+    fn match_cache_key_only_for_flat_tiles() {
+        let flat = Element::new(vec![0.5; 4], 0.5, None, None);
+        let textured = Element::new(vec![0.0, 1.0, 0.0, 1.0], 0.5, None, None);
+        let nearly_flat = Element::new(vec![0.5, 0.52, 0.5, 0.52], 0.51, None, None);
+        assert_eq!(Typesetter::match_cache_key(&flat), Some(128));
+        assert_eq!(Typesetter::match_cache_key(&nearly_flat), Some(130));
+        assert_eq!(Typesetter::match_cache_key(&textured), None);
+        assert_eq!(
+            Typesetter::match_cache_key(&Element::new(vec![], 0.0, None, None)),
+            None
+        );
+    }
+
+    #[test]
+    fn convert_from_luminance_grid_rejects_wrong_length() {
+        let typesetter = test_typesetter(&['A'], Config::default());
+        let result = typesetter.convert_from_luminance_grid(&[0.5; 10], 1, 1, &[]);
+        assert!(matches!(
+            result,
+            Err(TypistError::InputSize { actual: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn convert_from_luminance_grid_builds_rows() {
+        let typesetter = test_typesetter(&['A', 'I', 'M'], Config::default());
+        let typeset = typesetter.glyphs().unwrap();
+        let size = IMAGE_SIZE as usize;
+        let (columns, rows) = (3, 2);
+        let grid: Vec<f64> = (0..columns * rows * size * size)
+            .map(|i| (i % (columns * size)) as f64 / (columns * size) as f64)
+            .collect();
+
+        let result = typesetter
+            .convert_from_luminance_grid(&grid, columns as u32, rows as u32, typeset)
+            .unwrap();
+        assert_eq!(result.rows(), 2);
+        assert_eq!(result.columns(), 3);
+        assert_eq!(result.lines().len(), 2);
+        assert!(result.lines().iter().all(|l| l.chars().count() == 3));
+    }
+
+    #[test]
+    fn closest_luminance_index_empty_elements() {
+        let elements: Vec<Element> = vec![];
+        assert_eq!(Typesetter::closest_luminance_index(0.5, &elements), 0);
+    }
+
+    #[test]
+    fn closest_luminance_index_single_element() {
+        let elements = vec![Element::new(vec![0.0; 10], 0.5, Some('A'), None)];
+        assert_eq!(Typesetter::closest_luminance_index(0.5, &elements), 0);
+    }
+
+    #[test]
+    fn closest_luminance_index_multiple_elements() {
+        let elements = vec![
+            Element::new(vec![0.0; 10], 0.1, None, None),
+            Element::new(vec![0.0; 10], 0.5, None, None),
+            Element::new(vec![0.0; 10], 0.9, None, None),
+        ];
+        assert_eq!(Typesetter::closest_luminance_index(0.5, &elements), 1);
+        assert_eq!(Typesetter::closest_luminance_index(0.2, &elements), 0);
+        assert_eq!(Typesetter::closest_luminance_index(0.8, &elements), 2);
+    }
+
+    #[test]
+    fn dedup_elements_keeps_first_of_identical_glyphs() {
+        let elements = vec![
+            Element::new(vec![0.0, 1.0], 0.5, Some('b'), None),
+            Element::new(vec![1.0, 0.0], 0.5, Some('d'), None),
+            Element::new(vec![0.0, 1.0], 0.5, Some('ｂ'), None),
+            Element::new(vec![1e-9, 1.0], 0.5, Some('p'), None),
+            Element::new(vec![0.5, 0.5], 0.5, Some('q'), None),
+        ];
+        let kept: String = Typesetter::dedup_elements(elements)
+            .iter()
+            .filter_map(Element::character)
+            .collect();
+        assert_eq!(kept, "bdq");
+    }
+
+    #[test]
+    fn collapse_case_keeps_variant_farthest_from_others() {
+        let elements = vec![
+            Element::new(vec![], 0.5, Some('A'), None),
+            Element::new(vec![], 0.25, Some('.'), None),
+            Element::new(vec![], 0.875, Some('a'), None),
+            Element::new(vec![], 0.75, Some('B'), None),
+            Element::new(vec![], 0.0, Some('b'), None),
+            Element::new(vec![], 1.0, Some('c'), None),
+        ];
+        let kept: String = Typesetter::collapse_case(elements)
+            .iter()
+            .filter_map(|e| e.character())
+            .collect();
+        // 'A' sits between '.' and 'B', 'a' is crowded by 'B' and 'c';
+        // 'b' is farther from the rest than 'B'.
+        assert_eq!(kept, "A.bc");
+    }
+
+    #[test]
+    fn closest_candidates_ignore_order() {
+        let picture_element = Element::new(vec![0.5; 10], 0.5, None, None);
+        let typeset_elements = [
+            Element::new(vec![0.7; 10], 0.7, Some('D'), None),
+            Element::new(vec![0.5; 10], 0.5, Some('C'), None),
+            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
+        ];
+        let candidates = Typesetter::closest_candidates(0.5, typeset_elements.iter().collect(), 16);
+        let (best, _) =
+            Typesetter::best_match_element(&picture_element, candidates, &Pearson).unwrap();
+        assert_eq!(best.character(), Some('C'));
+    }
+
+    #[test]
+    fn band_candidates_keep_tonal_neighbors() {
+        let picture_element = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let typeset_elements = vec![
+            Element::new(vec![0.0, 0.5, 1.0], 0.0, Some('A'), None),
+            Element::new(vec![0.25, 0.5, 0.5], 0.5, Some('B'), None),
+            Element::new(vec![0.75, 0.5, 0.25], 0.625, Some('C'), None),
+            Element::new(vec![0.25, 0.5, 0.75], 1.0, Some('D'), None),
+        ];
+        for sorted in [true, false] {
+            // 'A' and 'D' correlate best but lie outside the band.
+            let candidates =
+                Typesetter::band_candidates(0.5, &typeset_elements, 0.25, sorted, None);
+            let (best, _) =
+                Typesetter::best_match_element(&picture_element, candidates, &Pearson).unwrap();
+            assert_eq!(best.character(), Some('B'));
+        }
+    }
+
+    #[test]
+    fn band_candidates_empty_band_takes_closest() {
+        let picture_element = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let typeset_elements = vec![
+            Element::new(vec![0.0, 0.5, 1.0], 0.0, Some('A'), None),
+            Element::new(vec![0.75, 0.5, 0.25], 0.875, Some('B'), None),
+        ];
+        let candidates = Typesetter::band_candidates(0.5, &typeset_elements, 0.125, true, None);
+        let (best, _) =
+            Typesetter::best_match_element(&picture_element, candidates, &Pearson).unwrap();
+        assert_eq!(best.character(), Some('B'));
+    }
+
+    #[test]
+    fn best_match_element_empty_candidates() {
+        let target = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
+        let candidates: Vec<Element> = vec![];
+        assert!(Typesetter::best_match_element(&target, &candidates, &Pearson).is_none());
+    }
+
+    #[test]
+    fn best_match_element_valid_candidates() {
+        let target = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
+        let candidates = vec![
+            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
+            Element::new(vec![0.5; 10], 0.5, Some('C'), None),
+            Element::new(vec![0.7; 10], 0.7, Some('D'), None),
+        ];
+        let best = Typesetter::best_match_element(&target, &candidates, &Pearson);
+        assert!(best.is_some());
+        assert_eq!(best.unwrap().0.characteristics(), &vec![0.5; 10]);
+    }
+
+    #[test]
+    fn best_match_element_skips_nan_candidates() {
+        let target = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let candidates = vec![
+            Element::new(vec![0.25, f64::NAN, 0.75], 0.5, Some('A'), None),
+            Element::new(vec![0.75, 0.5, 0.25], 0.5, Some('B'), None),
+        ];
+        for metric in [&Pearson as &dyn SimilarityMetric, &L1] {
+            let (best, score) =
+                Typesetter::best_match_element(&target, &candidates, metric).unwrap();
+            assert_eq!(best.character(), Some('B'));
+            assert!(score.is_finite());
+        }
+    }
+
+    #[test]
+    fn best_match_element_lower_is_better() {
+        let target = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let candidates = vec![
+            // correlates perfectly but is far darker.
+            Element::new(vec![0.0, 0.25, 0.5], 0.25, Some('A'), None),
+            Element::new(vec![0.25, 0.5, 0.5], 0.5, Some('B'), None),
+        ];
+        let (best, _) = Typesetter::best_match_element(&target, &candidates, &Pearson).unwrap();
+        assert_eq!(best.character(), Some('A'));
+        let (best, score) = Typesetter::best_match_element(&target, &candidates, &L1).unwrap();
+        assert_eq!(best.character(), Some('B'));
+        assert_eq!(score, 0.25 / 3.0);
+    }
+
+    #[test]
+    fn best_match_element_biases_by_weight() {
+        let target = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let close = || Element::new(vec![0.25, 0.5, 0.5], 0.5, Some('A'), None);
+        let closer = || Element::new(vec![0.25, 0.5, 0.625], 0.5, Some('B'), None);
+        for metric in [&Pearson as &dyn SimilarityMetric, &L1] {
+            let candidates = [close(), closer()];
+            let (best, _) = Typesetter::best_match_element(&target, &candidates, metric).unwrap();
+            assert_eq!(best.character(), Some('B'));
+
+            // an overused glyph loses to a slightly worse match.
+            let candidates = [close(), closer().with_weight(0.25)];
+            let (best, score) =
+                Typesetter::best_match_element(&target, &candidates, metric).unwrap();
+            assert_eq!(best.character(), Some('A'));
+            assert_eq!(
+                Some(score),
+                metric.score(target.characteristics(), best.characteristics())
+            );
+        }
+    }
+
+    #[test]
+    fn unstable_sort_keeps_tied_glyphs_grouped_by_luminance() {
+        // 60 glyphs sharing three tones, interleaved so that every tone has ties.
+        let tones = [0.75, 0.25, 0.5];
+        let elements: Vec<Element> = (0..60u32)
+            .map(|i| {
+                let tone = tones[i as usize % tones.len()];
+                let character = char::from_u32('Ａ' as u32 + i).unwrap();
+                Element::new(vec![tone; 4], tone, Some(character), None)
+            })
+            .collect();
+        let sorted = |unstable_sort| {
+            let config = Config {
+                unstable_sort,
+                ..Default::default()
+            };
+            let mut elements = elements.clone();
+            test_typesetter(&['A'], config).sort_elements(&mut elements);
+            elements
+        };
+        let (stable, unstable) = (sorted(false), sorted(true));
+
+        for typeset in [&stable, &unstable] {
+            let luminances: Vec<f64> = typeset.iter().map(Element::luminance).collect();
+            let expected: Vec<f64> = [0.25, 0.5, 0.75]
+                .iter()
+                .flat_map(|&tone| std::iter::repeat_n(tone, 20))
+                .collect();
+            assert_eq!(luminances, expected);
+        }
+        // the stable sort keeps tied glyphs in typeset order; the unstable one only
+        // keeps them within their tone.
+        for tone in stable.chunks(20) {
+            assert!(tone.windows(2).all(|w| w[0].character() < w[1].character()));
+        }
+        for (stable, unstable) in stable.chunks(20).zip(unstable.chunks(20)) {
+            let characters = |tone: &[Element]| -> HashSet<char> {
+                tone.iter().filter_map(Element::character).collect()
+            };
+            assert_eq!(characters(stable), characters(unstable));
+        }
+    }
+
+    #[test]
+    fn metric_topk_scores_only_the_tonally_closest() {
+        let picture_element = Element::new(vec![0.25, 0.5, 0.75], 0.5, None, None);
+        let typeset_elements = vec![
+            // correlates perfectly but is tonally farther.
+            Element::new(vec![0.25, 0.5, 0.75], 0.25, Some('A'), None),
+            Element::new(vec![0.75, 0.5, 0.25], 0.5, Some('B'), None),
+            Element::new(vec![0.5, 0.5, 0.25], 0.625, Some('C'), None),
+        ];
+        let search = |k| {
+            let sorted = Typesetter::tonal_window(0.5, &typeset_elements, k)
+                .iter()
+                .collect();
+            let linear = Typesetter::closest_candidates(0.5, typeset_elements.iter().collect(), k);
+            let band = Typesetter::band_candidates(0.5, &typeset_elements, 1.0, true, Some(k));
+            [sorted, linear, band].map(|candidates: Vec<&Element>| {
+                let (best, _) =
+                    Typesetter::best_match_element(&picture_element, candidates, &Pearson).unwrap();
+                best.character().unwrap()
+            })
+        };
+        assert_eq!(search(16), ['A'; 3]);
+        assert_eq!(search(1), ['B'; 3]);
+    }
+
+    #[test]
+    fn tonal_window_empty_typeset_is_empty() {
+        let typeset_elements: Vec<Element> = vec![];
+        assert!(Typesetter::tonal_window(0.5, &typeset_elements, 16).is_empty());
+    }
+
+    #[test]
+    fn tonal_window_valid_typeset_finds_a_match() {
+        let picture_element = Element::new(vec![0.5; 10], 0.5, Some('A'), None);
+        let typeset_elements = vec![
+            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
+            Element::new(vec![0.2; 10], 0.2, Some('B'), None),
+            Element::new(vec![0.5; 10], 0.5, Some('C'), None),
+            Element::new(vec![0.7; 10], 0.7, Some('D'), None),
+        ];
+        let candidates = Typesetter::tonal_window(0.5, &typeset_elements, 16);
+        let result = Typesetter::best_match_element(&picture_element, candidates, &Pearson);
+        assert!(result.is_some());
+        let (best_match, _) = result.unwrap();
+        assert_eq!(best_match.characteristics(), &vec![0.5; 10]);
+        assert_eq!(best_match.character(), Some('C'));
+    }
+
+    #[test]
+    fn extract_picture_elements_range_matches_two_pass() {
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 4, IMAGE_SIZE * 3, |x, y| {
+            let v = ((x * 7 + y * 3) % 256) as u8;
+            Rgba([v, 255 - v, v / 2, 255])
+        });
+        let typesetter = test_typesetter(&['A'], Config::default());
+        let (mut elements, range) = typesetter
+            .extract_picture_elements(&DynamicImage::ImageRgba8(image), IMAGE_SIZE, 4, 3)
+            .unwrap();
+
+        let luminances = elements.iter().map(|e| e.luminance());
+        let min = luminances.clone().fold(f64::INFINITY, f64::min);
+        let max = luminances.fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(range.bounds(), (min, max));
+
+        let mut two_pass = elements.clone();
+        for e in &mut two_pass {
+            e.normalized(min, max).unwrap();
+        }
+        Typesetter::normalize_elements(&mut elements, range).unwrap();
+        assert_eq!(elements, two_pass);
+    }
+
+    #[test]
+    fn extract_picture_elements_is_row_major() {
+        // each tile is a distinct shade, increasing row-major.
+        let image = RgbaImage::from_fn(IMAGE_SIZE * 3, IMAGE_SIZE * 2, |x, y| {
+            let tile = (y / IMAGE_SIZE) * 3 + x / IMAGE_SIZE;
+            let v = (tile * 40) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let typesetter = test_typesetter(&['A'], Config::default());
+        let (elements, _) = typesetter
+            .extract_picture_elements(&image, IMAGE_SIZE, 3, 2)
+            .unwrap();
+        let luminances: Vec<f64> = elements.iter().map(|e| e.luminance()).collect();
+        assert_eq!(luminances.len(), 6);
+        assert!(luminances.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn render_typeset_skips_unrenderable_glyphs() {
+        // the ASCII space has no outline and no raster image.
+        let typesetter = test_typesetter(&['A', ' '], Config::default());
+        let (elements, _) = typesetter.render_typeset(&['A', ' ']).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements[0].character(), Some('A'));
+
+        let result = typesetter.render_typeset(&[' ']);
+        assert!(matches!(result, Err(TypistError::EmptyTypeset)));
+    }
+
+    #[test]
+    fn assemble_strict_reports_unmatched_tiles() {
+        let config = Config {
+            strict: true,
+            ..Default::default()
+        };
+        let typesetter = test_typesetter(&['A'], config);
+        let picture = vec![Element::default(), Element::default()];
+        match typesetter.assemble(&picture, &[], 2, 1) {
+            Err(TypistError::UnmatchedTiles { positions }) => {
+                assert_eq!(positions, vec![(0, 0), (1, 0)])
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn assemble_non_strict_leaves_unmatched_tiles_blank() {
+        let typesetter = test_typesetter(&['A'], Config::default());
+        let picture = vec![Element::default(), Element::default()];
+        let result = typesetter.assemble(&picture, &[], 2, 1).unwrap();
+        assert_eq!(result.lines(), ["　　"]);
+        assert_eq!(result.scores(), [None, None]);
+    }
+
+    #[test]
+    fn unmatched_tiles_get_no_score_with_a_lower_is_better_metric() {
+        let config = Config {
+            metric: Arc::new(L1),
+            ..Default::default()
+        };
+        let typesetter = test_typesetter(&['A', 'M'], config);
+        let typeset = typesetter.glyphs().unwrap();
+        let size = (IMAGE_SIZE * IMAGE_SIZE) as usize;
+        // the second tile has too few characteristics to be compared with any glyph.
+        let picture = vec![
+            Element::new(vec![1.0; size], 1.0, None, None),
+            Element::new(vec![1.0; 4], 1.0, None, None),
+        ];
+
+        let result = typesetter.assemble(&picture, typeset, 2, 1).unwrap();
+        let matched = result.scores()[0].expect("the first tile matches");
+        assert_eq!(result.scores()[1], None);
+        assert_eq!(result.mean_score(), Some(matched));
+        assert_eq!(result.lines()[0].chars().nth(1), Some('　'));
+    }
+
+    #[test]
+    fn assemble_fills_unmatched_tiles_with_fill_char() {
+        let config = Config {
+            fill_char: '·',
+            ..Default::default()
+        };
+        let typesetter = test_typesetter(&['A'], config);
+        let picture = vec![Element::default(), Element::default()];
+        let result = typesetter.assemble(&picture, &[], 2, 1).unwrap();
+        assert_eq!(result.lines(), ["··"]);
+        assert_eq!(result.luminances(), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn typeset_elements_take_configured_weights() {
+        let config = Config {
+            glyph_weights: HashMap::from([('A', 0.5)]),
+            ..Default::default()
+        };
+        let typesetter = test_typesetter(&['A', 'M'], config);
+        let weights: HashMap<char, f64> = typesetter
+            .typeset_elements(&['A', 'M'])
+            .unwrap()
+            .iter()
+            .map(|e| (e.character().unwrap(), e.weight()))
+            .collect();
+        assert!((weights[&'A'] - 0.5).abs() < 1e-12);
+        assert_eq!(weights[&'M'], 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log;
+
+use crate::config::Config;
+use crate::conversion::ConversionResult;
+use crate::converter::Converter;
+use crate::error::{Result, TypistError};
+
+/// Extracts every frame of `video` into `dir` as numbered PNG files
+/// using the `ffmpeg` command-line tool, and returns their paths in order.
+pub fn extract_frames(video: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+
+    let status = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-i"])
+        .arg(video)
+        .arg(dir.join("frame_%05d.png"))
+        .status()
+        .map_err(|e| TypistError::Ffmpeg(format!("could not run ffmpeg ({e}); is it on PATH?")))?;
+    if !status.success() {
+        return Err(TypistError::Ffmpeg(format!(
+            "could not extract frames from {}",
+            video.display()
+        )));
+    }
+
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    frames.sort();
+    log::info!("Extracted {} frames from {}", frames.len(), video.display());
+
+    Ok(frames)
+}
+
+/// Converts each frame into typist-art with a single shared typeset.
+///
+/// The typeset is rendered once, and picture luminance is normalized against a
+/// fixed range so that frames stay tonally consistent with each other.
+pub fn convert_frames(
+    frames: &[PathBuf],
+    length: u32,
+    characters: &[char],
+    font: &[u8],
+    config: &Config,
+) -> Result<Vec<ConversionResult>> {
+    let config = Config {
+        fixed_range: true,
+        ..config.clone()
+    };
+
+    let converter = Converter::new(font, characters, config)?;
+    let mut results = Vec::with_capacity(frames.len());
+    for (i, path) in frames.iter().enumerate() {
+        results.push(converter.convert(&image::open(path)?, length)?);
+        log::debug!("Converted frame {}/{}", i + 1, frames.len());
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn write_gradient_frames(dir: &Path, count: u32) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| {
+                let image = RgbaImage::from_fn(80, 40, |x, y| {
+                    let v = ((x + y + i * 10) * 255 / 160).min(255) as u8;
+                    Rgba([v, v, v, 255])
+                });
+                let path = dir.join(format!("frame_{:05}.png", i + 1));
+                image.save(&path).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn convert_frames_converts_every_frame_at_the_same_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames = write_gradient_frames(dir.path(), 3);
+        let characters = ['A', 'M', '.', 'W'];
+
+        let results = convert_frames(
+            &frames,
+            8,
+            &characters,
+            crate::TEST_FONT_DATA,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), frames.len());
+        let rows = results[0].rows();
+        for result in &results {
+            assert_eq!((result.columns(), result.rows()), (8, rows));
+            assert_eq!(result.lines().len(), rows as usize);
+            assert!(
+                result
+                    .lines()
+                    .concat()
+                    .chars()
+                    .all(|c| characters.contains(&c))
+            );
+        }
+    }
+
+    #[test]
+    fn convert_frames_fails_on_an_unreadable_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut frames = write_gradient_frames(dir.path(), 1);
+        frames.push(dir.path().join("missing.png"));
+
+        let result = convert_frames(
+            &frames,
+            8,
+            &['A', 'M', '.', 'W'],
+            crate::TEST_FONT_DATA,
+            &Config::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn extract_frames_reports_a_missing_video() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = extract_frames(&dir.path().join("missing.mp4"), &dir.path().join("frames"));
+        assert!(matches!(result, Err(TypistError::Ffmpeg(_))));
+    }
+}
@@ -1,41 +1,928 @@
 use std::{
     io::{Write, stdout},
+    ops::{Deref, DerefMut},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use crossterm::{cursor, execute, style::Print, terminal};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal,
+};
+
+use crate::{PER_CHARACTER_DELAY_MS, PER_ROW_DELAY_MS, TAB_WIDTH, split_mix64};
+
+/// The unit in which the typist-art is revealed during animation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Reveal {
+    /// Prints one character at a time (the classic typing effect).
+    #[default]
+    Char,
+    /// Prints a whole row at a time with a single write per line.
+    Row,
+    /// Prints the whole art at once without any delay.
+    Instant,
+}
+
+/// The order in which characters are placed when revealing one character at a time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RevealOrder {
+    /// Left to right, then top to bottom, like typing.
+    #[default]
+    LrTb,
+    /// Right to left, then top to bottom.
+    RlTb,
+    /// Top to bottom, then left to right, column by column.
+    TbLr,
+    /// Clockwise from the outer edge toward the center.
+    Spiral,
+    /// A shuffled order, reproducible with the same seed.
+    Random,
+}
+
+/// A text attribute applied to the whole art.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TextAttribute {
+    /// The terminal's normal intensity.
+    #[default]
+    Normal,
+    /// Bold (or bright) text.
+    Bold,
+    /// Dim (faint) text.
+    Dim,
+}
+
+/// The newline style of plain text output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    /// A line feed (`\n`), as on Unix.
+    #[default]
+    Lf,
+    /// A carriage return and a line feed (`\r\n`), as on Windows.
+    Crlf,
+}
+
+impl LineEnding {
+    /// Returns the characters that end a line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Hides the terminal cursor while alive and shows it again when dropped,
+/// so the cursor (and any color or attribute set through the guard) is restored
+/// even if drawing fails midway. Dereferences to the wrapped writer.
+struct CursorGuard<'a, W: Write> {
+    writer: &'a mut W,
+    colored: bool,
+    attributed: bool,
+}
+
+impl<'a, W: Write> CursorGuard<'a, W> {
+    fn hide(writer: &'a mut W) -> std::io::Result<Self> {
+        execute!(writer, cursor::Hide)?;
+        Ok(CursorGuard {
+            writer,
+            colored: false,
+            attributed: false,
+        })
+    }
 
-use crate::PER_CHARACTER_DELAY_MS;
+    /// Sets the foreground color, which is reset when the guard is dropped.
+    fn set_foreground(&mut self, color: Color) -> std::io::Result<()> {
+        self.colored = true;
+        queue!(self.writer, SetForegroundColor(color))
+    }
+
+    /// Sets the background color, which is reset when the guard is dropped.
+    fn set_background(&mut self, color: Color) -> std::io::Result<()> {
+        self.colored = true;
+        queue!(self.writer, SetBackgroundColor(color))
+    }
+
+    /// Sets the text attribute, which is reset when the guard is dropped.
+    fn set_attribute(&mut self, attribute: Attribute) -> std::io::Result<()> {
+        self.attributed = true;
+        queue!(self.writer, SetAttribute(attribute))
+    }
+}
+
+impl<W: Write> Deref for CursorGuard<'_, W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        self.writer
+    }
+}
+
+impl<W: Write> DerefMut for CursorGuard<'_, W> {
+    fn deref_mut(&mut self) -> &mut W {
+        self.writer
+    }
+}
+
+impl<W: Write> Drop for CursorGuard<'_, W> {
+    fn drop(&mut self) {
+        if self.colored {
+            let _ = queue!(self.writer, ResetColor);
+        }
+        if self.attributed {
+            let _ = queue!(self.writer, SetAttribute(Attribute::NormalIntensity));
+        }
+        let _ = execute!(self.writer, cursor::Show);
+    }
+}
 
 /// A struct that serves as the View (V) in MVC.
 /// Specializes in displaying the generated typist-art in the terminal.
-pub struct View {}
+#[derive(Debug, Clone, Default)]
+pub struct View {
+    /// The unit in which the art is revealed.
+    pub reveal: Reveal,
+
+    /// The terminal row at which the art starts. Rows above it are left untouched.
+    pub top: u16,
+
+    /// A single foreground color for the whole art, or the terminal default.
+    pub fg: Option<Color>,
+
+    /// The text attribute of the whole art.
+    pub attr: TextAttribute,
+
+    /// The order in which characters are placed by the per-character reveal.
+    pub order: RevealOrder,
+
+    /// The seed of the shuffle used by the random reveal order.
+    pub seed: u64,
+
+    /// Per-cell background colors, indexed by row and then by character of the row.
+    /// Cells without a color keep the terminal background. Glyphs on a colored cell
+    /// are drawn in black or white, whichever contrasts more, unless `fg` is set.
+    pub bg: Vec<Vec<Option<Color>>>,
+
+    /// Timestamps in milliseconds from the start of the animation, in ascending
+    /// order. Each revealed unit waits for its beat instead of following the
+    /// fixed delay, so the n-th character (or row) appears at the n-th beat.
+    /// Units past the last beat fall back to the fixed delay.
+    pub beats: Vec<u64>,
+
+    /// The pause after each revealed unit, overriding the default of the reveal mode.
+    pub delay: Option<Duration>,
+}
 
 impl View {
-    /// Animates the given typist art line by line with a per-character delay.
-    pub fn animate(data: &[String]) -> std::io::Result<()> {
-        let mut stdout = stdout();
+    /// Animates the given typist art on stdout according to the configured reveal mode,
+    /// pausing between characters or rows by the configured delay, or by the
+    /// default delay of that mode.
+    pub fn animate(&self, data: &[String]) -> std::io::Result<()> {
+        let delay = self.delay.unwrap_or(match self.reveal {
+            Reveal::Char => Duration::from_millis(PER_CHARACTER_DELAY_MS),
+            Reveal::Row => Duration::from_millis(PER_ROW_DELAY_MS),
+            Reveal::Instant => Duration::ZERO,
+        });
+        Self::warn_if_clipped(self.extent(data));
+        self.animate_to(&mut stdout(), data, delay)
+    }
 
-        // clear the terminal.
-        execute!(
-            stdout,
-            terminal::Clear(terminal::ClearType::All),
-            cursor::MoveTo(0, 0),
-            cursor::Hide
-        )?;
-
-        for (y, line) in data.iter().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                execute!(stdout, cursor::MoveTo((x * 2) as u16, y as u16), Print(c))?;
-                stdout.flush()?;
-                thread::sleep(Duration::from_millis(PER_CHARACTER_DELAY_MS));
+    /// Animates the given typist art into `w`, pausing for `delay` after each
+    /// revealed unit (a character or a row, depending on the reveal mode), or
+    /// until the unit's beat while there are beats left. The instant reveal
+    /// ignores the beats.
+    pub fn animate_to<W: Write>(
+        &self,
+        w: &mut W,
+        data: &[String],
+        delay: Duration,
+    ) -> std::io::Result<()> {
+        // clear the terminal, keeping anything above the art.
+        self.clear(w)?;
+        let mut w = self.styled_guard(w)?;
+
+        let start = Instant::now();
+        match self.reveal {
+            Reveal::Char => {
+                for (i, (x, y, c, bg)) in self.reveal_sequence(data).into_iter().enumerate() {
+                    self.wait_for_beat(i, start);
+                    queue!(w, cursor::MoveTo(x, y))?;
+                    match bg {
+                        Some(color) => {
+                            self.print_on(&mut w, c, color)?;
+                            self.reset_background(&mut w)?;
+                        }
+                        None => queue!(w, Print(c))?,
+                    }
+                    w.flush()?;
+                    if i >= self.beats.len() {
+                        thread::sleep(delay);
+                    }
+                }
+            }
+            Reveal::Row => {
+                for (y, line) in data.iter().enumerate() {
+                    self.wait_for_beat(y, start);
+                    // NOTE: the per-character layout advances by each character's
+                    // display width, so a row written in one go lands on the same columns.
+                    queue!(w, cursor::MoveTo(0, self.top + y as u16))?;
+                    self.print_row(&mut w, y, line)?;
+                    w.flush()?;
+                    if y >= self.beats.len() {
+                        thread::sleep(delay);
+                    }
+                }
+            }
+            Reveal::Instant => {
+                for (y, line) in data.iter().enumerate() {
+                    queue!(w, cursor::MoveTo(0, self.top + y as u16))?;
+                    self.print_row(&mut w, y, line)?;
+                }
+                w.flush()?;
             }
         }
 
         // move cursor under typist-art after animation
-        execute!(stdout, cursor::MoveTo(0, data.len() as u16), cursor::Show)?;
+        execute!(w, cursor::MoveTo(0, self.top + data.len() as u16))?;
+
+        Ok(())
+    }
+
+    /// Sleeps until the beat of the `index`-th revealed unit, counted from `start`,
+    /// if there is one.
+    fn wait_for_beat(&self, index: usize, start: Instant) {
+        if let Some(wait) = beat_wait(&self.beats, index, start.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Splits arbitrary text into lines for animation, keeping blank lines.
+    /// Tabs are expanded to spaces up to the next multiple of [`TAB_WIDTH`]
+    /// columns, since the reveal places every character at its own position.
+    ///
+    /// [`TAB_WIDTH`]: crate::TAB_WIDTH
+    pub fn text_lines(text: &str) -> Vec<String> {
+        text.lines()
+            .map(|line| {
+                let mut expanded = String::with_capacity(line.len());
+                let mut column = 0;
+                for c in line.chars() {
+                    if c == '\t' {
+                        let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                        expanded.extend(std::iter::repeat_n(' ', spaces as usize));
+                        column += spaces;
+                    } else {
+                        expanded.push(c);
+                        column += char_width(c);
+                    }
+                }
+                expanded
+            })
+            .collect()
+    }
+
+    /// Prints the given typist art on stdout as plain text, without animation,
+    /// ending every row with `ending`.
+    pub fn print(data: &[String], ending: LineEnding) -> std::io::Result<()> {
+        Self::print_to(&mut stdout().lock(), data, ending)
+    }
+
+    /// Writes the given typist art into `w` as rows ended by `ending`, with no
+    /// escape codes or cursor movement, so it lands in the scrollback and can be copied.
+    pub fn print_to<W: Write>(
+        w: &mut W,
+        data: &[String],
+        ending: LineEnding,
+    ) -> std::io::Result<()> {
+        for line in data {
+            write!(w, "{line}{}", ending.as_str())?;
+        }
+        w.flush()
+    }
+
+    /// Plays a sequence of pre-generated frames, redrawing each in place at `fps` frames per second.
+    ///
+    /// Frames are scheduled against the wall clock from the start of playback, so
+    /// the time spent drawing does not accumulate as drift. If drawing falls behind,
+    /// frames are skipped to catch up instead of being shown late; the last frame
+    /// is always shown.
+    pub fn play_frames(&self, frames: &[Vec<String>], fps: f64) -> std::io::Result<()> {
+        self.play(frames, fps, false)
+    }
+
+    /// Like [`View::play_frames`], but starts over after the last frame and keeps
+    /// playing until Ctrl-C, Esc, or `q` is pressed. The terminal is in raw mode
+    /// during playback so the keys are read instead of interrupting the process.
+    pub fn loop_frames(&self, frames: &[Vec<String>], fps: f64) -> std::io::Result<()> {
+        self.play(frames, fps, true)
+    }
+
+    /// Plays the frames once, or until a quit key is pressed when `looped`.
+    fn play(&self, frames: &[Vec<String>], fps: f64, looped: bool) -> std::io::Result<()> {
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid frame rate: {fps}"),
+            ));
+        }
+        let interval = Duration::from_secs_f64(1.0 / fps);
+        let mut stdout = stdout();
+        Self::warn_if_clipped(
+            frames
+                .iter()
+                .map(|frame| self.extent(frame))
+                .fold((0, 0), |(w, h), (fw, fh)| (w.max(fw), h.max(fh))),
+        );
+
+        let _raw = if looped {
+            Some(RawModeGuard::enable()?)
+        } else {
+            None
+        };
+        self.clear(&mut stdout)?;
+        let mut w = self.styled_guard(&mut stdout)?;
+
+        let mut start = Instant::now();
+        let mut height = 0;
+        let mut index = 0;
+        let mut skipped = 0;
+        while let Some(frame) = frames.get(index) {
+            for (y, line) in frame.iter().enumerate() {
+                queue!(w, cursor::MoveTo(0, self.top + y as u16))?;
+                w.write_all(line.as_bytes())?;
+                queue!(w, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+            }
+            // erase rows left over from a taller previous frame.
+            queue!(
+                w,
+                cursor::MoveTo(0, self.top + frame.len() as u16),
+                terminal::Clear(terminal::ClearType::FromCursorDown)
+            )?;
+            w.flush()?;
+            height = height.max(frame.len());
+
+            let elapsed = start.elapsed();
+            let next = next_frame(index, elapsed, interval, frames.len());
+            skipped += next - index - 1;
+            index = next;
+            let wait = interval.mul_f64(index as f64).checked_sub(elapsed);
+            if looped {
+                if quit_requested(wait.unwrap_or_default())? {
+                    break;
+                }
+                if index >= frames.len() {
+                    // NOTE: shift the schedule rather than restart it, so loops do not drift either.
+                    start += interval.mul_f64(frames.len() as f64);
+                    index = 0;
+                }
+            } else if let Some(wait) = wait {
+                thread::sleep(wait);
+            }
+        }
+        if skipped > 0 {
+            log::debug!("Skipped {skipped} of {} frames to keep up", frames.len());
+        }
+
+        // move cursor under the frames after playback
+        execute!(w, cursor::MoveTo(0, self.top + height as u16))?;
 
         Ok(())
     }
+
+    /// Writes a row of the art at the cursor: in a single write without background
+    /// colors, otherwise cell by cell, resetting the background at the end of the row.
+    fn print_row<W: Write>(
+        &self,
+        w: &mut CursorGuard<'_, W>,
+        row: usize,
+        line: &str,
+    ) -> std::io::Result<()> {
+        if self
+            .bg
+            .get(row)
+            .is_none_or(|colors| colors.iter().all(Option::is_none))
+        {
+            return w.write_all(line.as_bytes());
+        }
+        for (column, c) in line.chars().enumerate() {
+            match self.background(row, column) {
+                Some(color) => self.print_on(w, c, color)?,
+                None => {
+                    self.reset_background(w)?;
+                    queue!(w, Print(c))?;
+                }
+            }
+        }
+        self.reset_background(w)
+    }
+
+    /// Writes a character at the cursor over the `background` color.
+    fn print_on<W: Write>(
+        &self,
+        w: &mut CursorGuard<'_, W>,
+        c: char,
+        background: Color,
+    ) -> std::io::Result<()> {
+        w.set_background(background)?;
+        if self.fg.is_none() {
+            queue!(w, SetForegroundColor(contrasting(background)))?;
+        }
+        queue!(w, Print(c))
+    }
+
+    /// Restores the terminal background and the configured foreground after colored cells.
+    fn reset_background<W: Write>(&self, w: &mut CursorGuard<'_, W>) -> std::io::Result<()> {
+        queue!(
+            w,
+            SetBackgroundColor(Color::Reset),
+            SetForegroundColor(self.fg.unwrap_or(Color::Reset))
+        )
+    }
+
+    /// Returns the background color of the cell at `row` and character index `column`.
+    fn background(&self, row: usize, column: usize) -> Option<Color> {
+        self.bg.get(row)?.get(column).copied().flatten()
+    }
+
+    /// Returns the terminal position, character, and background color of every
+    /// cell of the art, in the configured reveal order.
+    fn reveal_sequence(&self, data: &[String]) -> Vec<(u16, u16, char, Option<Color>)> {
+        // lay the cells out on a grid of character indices, keeping their display column.
+        let grid: Vec<Vec<(u16, char)>> = data
+            .iter()
+            .map(|line| {
+                let mut x = 0;
+                line.chars()
+                    .map(|c| {
+                        let cell = (x, c);
+                        x += char_width(c);
+                        cell
+                    })
+                    .collect()
+            })
+            .collect();
+        let cell = |row: usize, column: usize| {
+            grid[row]
+                .get(column)
+                .map(|&(x, c)| (x, self.top + row as u16, c, self.background(row, column)))
+        };
+        let rows = grid.len();
+        let columns = grid.iter().map(Vec::len).max().unwrap_or(0);
+
+        match self.order {
+            RevealOrder::LrTb => (0..rows)
+                .flat_map(|row| (0..columns).map(move |column| (row, column)))
+                .filter_map(|(row, column)| cell(row, column))
+                .collect(),
+            RevealOrder::RlTb => (0..rows)
+                .flat_map(|row| (0..columns).rev().map(move |column| (row, column)))
+                .filter_map(|(row, column)| cell(row, column))
+                .collect(),
+            RevealOrder::TbLr => (0..columns)
+                .flat_map(|column| (0..rows).map(move |row| (row, column)))
+                .filter_map(|(row, column)| cell(row, column))
+                .collect(),
+            RevealOrder::Spiral => spiral(rows, columns)
+                .into_iter()
+                .filter_map(|(row, column)| cell(row, column))
+                .collect(),
+            RevealOrder::Random => {
+                let mut cells: Vec<_> = (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (row, column)))
+                    .filter_map(|(row, column)| cell(row, column))
+                    .collect();
+                // Fisher-Yates shuffle driven by SplitMix64.
+                let mut state = self.seed;
+                for i in (1..cells.len()).rev() {
+                    let j = (split_mix64(&mut state) % (i as u64 + 1)) as usize;
+                    cells.swap(i, j);
+                }
+                cells
+            }
+        }
+    }
+
+    /// Logs a warning when an art of the given extent does not fit the terminal
+    /// window, since drawing past its edges clips or garbles the output.
+    fn warn_if_clipped((width, height): (u16, u16)) {
+        let Ok((columns, rows)) = terminal::size() else {
+            return;
+        };
+        if width > columns || height > rows {
+            log::warn!(
+                "The art needs {width}x{height} cells but the terminal is {columns}x{rows}; \
+                 enlarge the window or use a smaller LENGTH to avoid garbled output"
+            );
+        }
+    }
+
+    /// Returns the number of terminal columns and rows the art occupies, counting
+    /// the rows above `top` and the line the cursor moves to afterwards.
+    fn extent(&self, data: &[String]) -> (u16, u16) {
+        let width = data
+            .iter()
+            .map(|line| line.chars().map(char_width).sum::<u16>())
+            .max()
+            .unwrap_or(0);
+
+        (width, self.top + data.len() as u16 + 1)
+    }
+
+    /// Hides the cursor and applies the configured color and attribute,
+    /// all of which are restored when the returned guard is dropped.
+    fn styled_guard<'a, W: Write>(&self, w: &'a mut W) -> std::io::Result<CursorGuard<'a, W>> {
+        let mut guard = CursorGuard::hide(w)?;
+        if let Some(color) = self.fg {
+            guard.set_foreground(color)?;
+        }
+        match self.attr {
+            TextAttribute::Normal => {}
+            TextAttribute::Bold => guard.set_attribute(Attribute::Bold)?,
+            TextAttribute::Dim => guard.set_attribute(Attribute::Dim)?,
+        }
+
+        Ok(guard)
+    }
+
+    /// Clears the terminal from the `top` row down and moves the cursor there.
+    fn clear<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        if self.top == 0 {
+            execute!(
+                w,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            )
+        } else {
+            execute!(
+                w,
+                cursor::MoveTo(0, self.top),
+                terminal::Clear(terminal::ClearType::FromCursorDown)
+            )
+        }
+    }
+}
+
+/// Returns the index of the frame to draw after frame `index` of `count`, `elapsed`
+/// into playback at one frame per `interval`: the next one, or the one due by the
+/// clock when drawing has fallen behind. Past the last frame, returns `count`.
+fn next_frame(index: usize, elapsed: Duration, interval: Duration, count: usize) -> usize {
+    if index + 1 >= count {
+        return count;
+    }
+    let due = (elapsed.as_secs_f64() / interval.as_secs_f64()) as usize;
+
+    (index + 1).max(due.min(count - 1))
+}
+
+/// Returns how long the `index`-th revealed unit still has to wait for its beat
+/// after `elapsed`, zero if the beat has passed, or `None` if the beats ran out.
+fn beat_wait(beats: &[u64], index: usize, elapsed: Duration) -> Option<Duration> {
+    let beat = Duration::from_millis(*beats.get(index)?);
+    Some(beat.saturating_sub(elapsed))
+}
+
+/// Keeps the terminal in raw mode while alive, so key presses such as Ctrl-C
+/// are read as events, and restores cooked mode when dropped.
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Waits up to `timeout` for a key press and returns whether it asks to quit:
+/// Ctrl-C, Esc, or `q`. Other events are discarded.
+fn quit_requested(timeout: Duration) -> std::io::Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !event::poll(remaining)? {
+            return Ok(false);
+        }
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && is_quit_key(key)
+        {
+            return Ok(true);
+        }
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+    }
+}
+
+/// Whether a key event is Ctrl-C, Esc, or `q`.
+fn is_quit_key(key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        KeyCode::Char('q') | KeyCode::Esc => true,
+        _ => false,
+    }
+}
+
+/// Returns the `(row, column)` cells of a `rows`×`columns` grid, clockwise
+/// from the top-left corner toward the center.
+fn spiral(rows: usize, columns: usize) -> Vec<(usize, usize)> {
+    let mut cells = Vec::with_capacity(rows * columns);
+    let (mut top, mut bottom, mut left, mut right) = (0, rows, 0, columns);
+    while top < bottom && left < right {
+        cells.extend((left..right).map(|column| (top, column)));
+        cells.extend((top + 1..bottom).map(|row| (row, right - 1)));
+        if top + 1 < bottom {
+            cells.extend((left..right - 1).rev().map(|column| (bottom - 1, column)));
+        }
+        if left + 1 < right {
+            cells.extend((top + 1..bottom - 1).rev().map(|row| (row, left)));
+        }
+        top += 1;
+        bottom -= 1;
+        left += 1;
+        right -= 1;
+    }
+
+    cells
+}
+
+/// Returns black or white, whichever is more legible on `background`.
+/// Colors other than RGB are treated as dark.
+fn contrasting(background: Color) -> Color {
+    let Color::Rgb { r, g, b } = background else {
+        return Color::White;
+    };
+    if crate::color::Color::luminance_from_rgba(&[r, g, b, 255]) > 0.5 {
+        Color::Black
+    } else {
+        Color::White
+    }
+}
+
+/// Returns the number of terminal columns `c` occupies: 2 for wide (CJK,
+/// full-width, and emoji) characters, 1 for everything else, including Braille.
+pub(crate) fn char_width(c: char) -> u16 {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3040..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(view: &View, data: &[String]) -> String {
+        let mut buffer = Vec::new();
+        view.animate_to(&mut buffer, data, Duration::ZERO).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn animate_to_row_writes_whole_lines() {
+        let view = View {
+            reveal: Reveal::Row,
+            ..Default::default()
+        };
+        let output = render(&view, &["ＡＢ".to_string(), "ＣＤ".to_string()]);
+        assert!(output.contains("ＡＢ"));
+        assert!(output.contains("ＣＤ"));
+    }
+
+    #[test]
+    fn animate_to_char_writes_every_character() {
+        let output = render(&View::default(), &["ＡＢ".to_string()]);
+        assert!(output.contains('Ａ'));
+        assert!(output.contains('Ｂ'));
+        assert!(!output.contains("ＡＢ"));
+    }
+
+    #[test]
+    fn next_frame_skips_late_frames_but_not_the_last() {
+        let interval = Duration::from_millis(100);
+        // on time: the next frame.
+        assert_eq!(next_frame(0, Duration::from_millis(30), interval, 10), 1);
+        assert_eq!(next_frame(3, Duration::from_millis(350), interval, 10), 4);
+        // behind: jump to the frame due now.
+        assert_eq!(next_frame(1, Duration::from_millis(560), interval, 10), 5);
+        // far behind: the last frame is still shown, then playback ends.
+        assert_eq!(next_frame(2, Duration::from_secs(5), interval, 10), 9);
+        assert_eq!(next_frame(9, Duration::from_secs(5), interval, 10), 10);
+    }
+
+    #[test]
+    fn text_lines_keep_blank_lines_and_expand_tabs() {
+        assert_eq!(View::text_lines("one\n\n\nfour\n"), ["one", "", "", "four"]);
+        assert_eq!(View::text_lines("a\tb\r\n\tc"), ["a       b", "        c"]);
+        // wide characters take two columns before the tab stop.
+        assert_eq!(View::text_lines("あ\tb"), ["あ      b"]);
+        assert!(View::text_lines("").is_empty());
+    }
+
+    #[test]
+    fn beat_wait_until_each_beat_then_falls_back() {
+        let beats = [100, 250, 250];
+        let at = Duration::from_millis;
+        assert_eq!(beat_wait(&beats, 0, at(0)), Some(at(100)));
+        assert_eq!(beat_wait(&beats, 1, at(120)), Some(at(130)));
+        // late, or on a repeated beat: no wait.
+        assert_eq!(beat_wait(&beats, 2, at(260)), Some(Duration::ZERO));
+        assert_eq!(beat_wait(&beats, 3, at(260)), None);
+        assert_eq!(beat_wait(&[], 0, at(0)), None);
+    }
+
+    #[test]
+    fn animate_to_reveals_on_beats() {
+        let view = View {
+            reveal: Reveal::Row,
+            beats: vec![0, 30],
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let mut buffer = Vec::new();
+        view.animate_to(&mut buffer, &vec!["ab".to_string(); 3], Duration::ZERO)
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(String::from_utf8(buffer).unwrap().matches("ab").count(), 3);
+    }
+
+    #[test]
+    fn quit_keys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert!(is_quit_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(is_quit_key(key(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert!(is_quit_key(key(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(!is_quit_key(key(KeyCode::Char('c'), KeyModifiers::NONE)));
+        assert!(!is_quit_key(key(KeyCode::Enter, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn char_width_full_and_half_width() {
+        assert_eq!(char_width('Ａ'), 2);
+        assert_eq!(char_width('あ'), 2);
+        assert_eq!(char_width(crate::FULL_WIDTH_SPACE), 2);
+        assert_eq!(char_width('⣿'), 1);
+        assert_eq!(char_width('A'), 1);
+    }
+
+    fn order(view: &View, data: &[&str]) -> String {
+        let data: Vec<String> = data.iter().map(|line| line.to_string()).collect();
+        view.reveal_sequence(&data)
+            .into_iter()
+            .map(|(_, _, c, _)| c)
+            .collect()
+    }
+
+    #[test]
+    fn reveal_sequence_orders() {
+        let data = ["abc", "def", "gh"];
+        let view = |order| View {
+            order,
+            ..Default::default()
+        };
+        assert_eq!(order(&view(RevealOrder::LrTb), &data), "abcdefgh");
+        assert_eq!(order(&view(RevealOrder::RlTb), &data), "cbafedhg");
+        assert_eq!(order(&view(RevealOrder::TbLr), &data), "adgbehcf");
+        assert_eq!(order(&view(RevealOrder::Spiral), &data), "abcfhgde");
+    }
+
+    #[test]
+    fn reveal_sequence_keeps_display_columns() {
+        let view = View {
+            order: RevealOrder::RlTb,
+            top: 2,
+            ..Default::default()
+        };
+        let cells = view.reveal_sequence(&["Ａb".to_string()]);
+        assert_eq!(cells, vec![(2, 2, 'b', None), (0, 2, 'Ａ', None)]);
+    }
+
+    #[test]
+    fn reveal_sequence_random_is_seeded() {
+        let data = ["abcdefgh", "ijklmnop"];
+        let view = |seed| View {
+            order: RevealOrder::Random,
+            seed,
+            ..Default::default()
+        };
+        let shuffled = order(&view(7), &data);
+        assert_eq!(shuffled, order(&view(7), &data));
+        assert_ne!(shuffled, order(&view(8), &data));
+        let mut sorted: Vec<char> = shuffled.chars().collect();
+        sorted.sort();
+        assert_eq!(sorted.into_iter().collect::<String>(), "abcdefghijklmnop");
+    }
+
+    #[test]
+    fn extent_counts_display_columns_and_rows() {
+        let view = View {
+            top: 3,
+            ..Default::default()
+        };
+        let data = ["ＡＢ".to_string(), "⣿⣿⣿⣿⣿".to_string()];
+        assert_eq!(view.extent(&data), (5, 6));
+        assert_eq!(view.extent(&[]), (0, 4));
+    }
+
+    #[test]
+    fn animate_to_sets_and_resets_foreground() {
+        let view = View {
+            fg: Some(Color::Rgb { r: 255, g: 0, b: 0 }),
+            ..Default::default()
+        };
+        let output = render(&view, &["Ａ".to_string()]);
+        let set = output.find("\x1b[38;2;255;0;0m").unwrap();
+        let reset = output.rfind("\x1b[0m").unwrap();
+        assert!(set < output.find('Ａ').unwrap());
+        assert!(reset > output.find('Ａ').unwrap());
+    }
+
+    #[test]
+    fn animate_to_sets_and_resets_attribute() {
+        let view = View {
+            attr: TextAttribute::Bold,
+            ..Default::default()
+        };
+        let output = render(&view, &["Ａ".to_string()]);
+        let set = output.find("\x1b[1m").unwrap();
+        let reset = output.rfind("\x1b[22m").unwrap();
+        assert!(set < output.find('Ａ').unwrap());
+        assert!(reset > output.find('Ａ').unwrap());
+    }
+
+    #[test]
+    fn animate_to_paints_backgrounds_with_contrasting_glyphs() {
+        let white = Color::Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let view = View {
+            reveal: Reveal::Row,
+            bg: vec![vec![Some(white), None]],
+            ..Default::default()
+        };
+        let output = render(&view, &["ＡＢ".to_string()]);
+        let a = output.find('Ａ').unwrap();
+        let b = output.find('Ｂ').unwrap();
+        assert!(output[..a].contains("\x1b[48;2;255;255;255m"));
+        assert!(output[..a].contains("\x1b[38;5;0m"));
+        // the uncolored cell and the end of the row restore the terminal background.
+        assert!(output[a..b].contains("\x1b[49m"));
+        assert!(output[b..].contains("\x1b[49m"));
+        assert!(output.rfind("\x1b[0m").unwrap() > b);
+    }
+
+    #[test]
+    fn contrasting_picks_black_or_white() {
+        assert_eq!(
+            contrasting(Color::Rgb {
+                r: 250,
+                g: 240,
+                b: 200
+            }),
+            Color::Black
+        );
+        assert_eq!(contrasting(Color::Rgb { r: 20, g: 0, b: 80 }), Color::White);
+    }
+
+    #[test]
+    fn print_to_writes_only_characters_and_newlines() {
+        let mut buffer = Vec::new();
+        let lines = ["ＡＢ".to_string(), "ＣＤ".to_string()];
+        View::print_to(&mut buffer, &lines, LineEnding::Lf).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "ＡＢ\nＣＤ\n");
+
+        let mut buffer = Vec::new();
+        View::print_to(&mut buffer, &lines, LineEnding::Crlf).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "ＡＢ\r\nＣＤ\r\n");
+    }
+
+    #[test]
+    fn animate_to_restores_cursor() {
+        let output = render(&View::default(), &["Ａ".to_string()]);
+        // hide, then show the cursor again at the end.
+        assert!(output.contains("\x1b[?25l"));
+        assert!(output.ends_with("\x1b[?25h"));
+    }
 }